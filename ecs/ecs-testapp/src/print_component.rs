@@ -1,27 +1,29 @@
-use std::cell::RefCell;
-
 pub struct PrintComponent {
-    text_to_print: RefCell<String>,
+    text_to_print: String,
+}
+
+impl Default for PrintComponent {
+    fn default() -> PrintComponent {
+        PrintComponent::new()
+    }
 }
 
 impl PrintComponent {
     pub fn new() -> PrintComponent {
-        PrintComponent {
-            text_to_print: RefCell::new(String::from("default text\n")),
-        }
+        PrintComponent { text_to_print: String::from("default text\n") }
     }
 
     pub fn test_print(&self) {
-        print!("test print {}\n", self.text_to_print.borrow());
+        println!("test print {}", self.text_to_print);
     }
 
-    pub fn change_text(&self, new_text: String) {
-        *self.text_to_print.borrow_mut() = new_text;
+    pub fn change_text(&mut self, new_text: String) {
+        self.text_to_print = new_text;
     }
 }
 
 impl engine::entity::Component for PrintComponent {
-    fn update(&self) {
+    fn update(&mut self, _context: &mut engine::entity::UpdateContext) {
         print!("update print ");
         self.test_print();
     }
@@ -0,0 +1,89 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::parse_macro_input;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Index;
+use syn::Member;
+
+// Implements engine::reflect::Reflect for structs. Fields marked with #[reflect(ignore)] are not listed,
+// all other fields must implement Reflect. Tuple struct fields are named by their index.
+#[proc_macro_derive(Reflect, attributes(reflect))]
+pub fn derive_reflect(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => return Err(syn::Error::new_spanned(&input.ident, "Reflect can only be derived for structs")),
+    };
+    let mut members = vec![];
+    let mut types = vec![];
+    for (index, field) in fields.iter().enumerate() {
+        if is_ignored(field)? {
+            continue;
+        }
+        members.push(match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index::from(index)),
+        });
+        types.push(&field.ty);
+    }
+    let names: Vec<String> = members
+        .iter()
+        .map(|x| match x {
+            Member::Named(ident) => ident.to_string(),
+            Member::Unnamed(index) => index.index.to_string(),
+        })
+        .collect();
+
+    let ident = &input.ident;
+    let type_name = ident.to_string();
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::engine::reflect::Reflect for #ident #type_generics #where_clause {
+            fn get_type_name(&self) -> &'static str {
+                #type_name
+            }
+
+            fn get_fields(&self) -> ::std::vec::Vec<::engine::reflect::FieldInfo> {
+                vec![#(::engine::reflect::FieldInfo::new(#names, ::std::any::type_name::<#types>())),*]
+            }
+
+            fn get_field(&self, name: &str) -> ::std::option::Option<&dyn ::engine::reflect::Reflect> {
+                match name {
+                    #(#names => ::std::option::Option::Some(&self.#members),)*
+                    _ => ::std::option::Option::None,
+                }
+            }
+
+            fn get_field_mut(&mut self, name: &str) -> ::std::option::Option<&mut dyn ::engine::reflect::Reflect> {
+                match name {
+                    #(#names => ::std::option::Option::Some(&mut self.#members),)*
+                    _ => ::std::option::Option::None,
+                }
+            }
+        }
+    })
+}
+
+fn is_ignored(field: &syn::Field) -> syn::Result<bool> {
+    let mut ignored = false;
+    for attribute in field.attrs.iter().filter(|x| x.path().is_ident("reflect")) {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("ignore") {
+                ignored = true;
+                Ok(())
+            } else {
+                Err(meta.error("unknown reflect attribute"))
+            }
+        })?;
+    }
+    Ok(ignored)
+}
@@ -0,0 +1,65 @@
+use criterion::black_box;
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use engine::entity::EntityId;
+use engine::scene::Scene;
+
+fn create_scene(entity_count: usize) -> (Scene, Vec<EntityId>) {
+    let mut scene = Scene::new();
+    let ids = (0..entity_count)
+        .map(|x| scene.create_entity_with_name(format!("entity {}", x)))
+        .collect();
+    (scene, ids)
+}
+
+// Lookups should take the same time whether the scene has a thousand or a hundred thousand entities
+fn lookup(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("entity_lookup");
+    for entity_count in [1_000, 10_000, 100_000] {
+        let (mut scene, ids) = create_scene(entity_count);
+        let last = *ids.last().unwrap();
+        let last_name = format!("entity {}", entity_count - 1);
+        group.bench_with_input(BenchmarkId::new("by_id", entity_count), &last, |bencher, id| {
+            bencher.iter(|| scene.get_entity_by_id(black_box(*id)).is_some())
+        });
+        group.bench_with_input(BenchmarkId::new("by_name", entity_count), last_name.as_str(), |bencher, name| {
+            bencher.iter(|| scene.get_entity_by_name(black_box(name)))
+        });
+        group.bench_with_input(BenchmarkId::new("rename", entity_count), &last, |bencher, id| {
+            let mut toggle = false;
+            bencher.iter(|| {
+                toggle = !toggle;
+                scene.set_entity_name(*id, String::from(if toggle { "renamed" } else { "entity" }))
+            })
+        });
+    }
+    group.finish();
+}
+
+// Renaming one of many entities that share a name should not depend on how many share it
+fn duplicate_names(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("duplicate_names");
+    for entity_count in [1_000, 10_000, 100_000] {
+        let mut scene = Scene::new();
+        let ids: Vec<EntityId> = (0..entity_count)
+            .map(|_| scene.create_entity_with_name(String::from("soldier")))
+            .collect();
+        let middle = ids[entity_count / 2];
+        group.bench_with_input(BenchmarkId::new("rename", entity_count), &middle, |bencher, id| {
+            let mut toggle = false;
+            bencher.iter(|| {
+                toggle = !toggle;
+                scene.set_entity_name(*id, String::from(if toggle { "captain" } else { "soldier" }))
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("by_name", entity_count), "soldier", |bencher, name| {
+            bencher.iter(|| scene.get_entity_by_name(black_box(name)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, lookup, duplicate_names);
+criterion_main!(benches);
@@ -0,0 +1,221 @@
+use criterion::black_box;
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use engine::entity::Component;
+use engine::entity::EntityId;
+use engine::scene::Scene;
+use engine::storage::StorageType;
+use std::any::Any;
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+struct Position {
+    x: f32,
+}
+
+struct Velocity {
+    x: f32,
+}
+
+// The same status effect stored in the archetype tables and in a sparse set
+struct TableEffect {
+    strength: f32,
+}
+
+struct SparseEffect {
+    strength: f32,
+}
+
+impl Component for Position {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+impl Component for Velocity {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+impl Component for TableEffect {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+impl Component for SparseEffect {
+    const STORAGE: StorageType = StorageType::SparseSet;
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+fn create_scene(entity_count: usize) -> (Scene, Vec<EntityId>) {
+    let mut scene = Scene::new();
+    let ids = (0..entity_count)
+        .map(|x| {
+            let id = scene.create_entity();
+            scene.add_component(id, Position { x: x as f32 });
+            scene.add_component(id, Velocity { x: 1.0 });
+            id
+        })
+        .collect();
+    (scene, ids)
+}
+
+// The baseline the two storage types are measured against: the entity that owned its components
+// before archetypes, with a list of shared, borrow-checked components per type. Component has an
+// associated constant now, so the old trait object gets its own trait
+trait MapComponent {
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: Component + 'static> MapComponent for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[derive(Default)]
+struct MapEntity {
+    components: HashMap<TypeId, Vec<Rc<RefCell<dyn MapComponent>>>>,
+}
+
+struct MapStorage {
+    entities: Vec<MapEntity>,
+}
+
+impl MapStorage {
+    fn new(entity_count: usize) -> MapStorage {
+        let mut storage = MapStorage {
+            entities: (0..entity_count).map(|_| MapEntity::default()).collect(),
+        };
+        for x in 0..entity_count {
+            storage.add(x, Position { x: x as f32 });
+            storage.add(x, Velocity { x: 1.0 });
+        }
+        storage
+    }
+
+    fn add<T: Component + 'static>(&mut self, entity: usize, component: T) {
+        let components = &mut self.entities[entity].components;
+        components
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .push(Rc::new(RefCell::new(component)));
+    }
+
+    fn remove<T: Component + 'static>(&mut self, entity: usize) {
+        self.entities[entity].components.remove(&TypeId::of::<T>());
+    }
+
+    fn sum<A: Component + 'static, B: Component + 'static>(&self, value: impl Fn(&A, &B) -> f32) -> f32 {
+        let mut sum = 0.0;
+        for id in 0..self.entities.len() {
+            let a = self.entities[id].components[&TypeId::of::<A>()][0].borrow();
+            let b = self.entities[id].components[&TypeId::of::<B>()][0].borrow();
+            sum += value(a.as_any().downcast_ref::<A>().unwrap(), b.as_any().downcast_ref::<B>().unwrap());
+        }
+        sum
+    }
+}
+
+// Adding and removing a table component moves the entity's other components between archetypes
+fn add_remove(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("storage_add_remove");
+    for entity_count in [1_000, 10_000] {
+        let (mut scene, ids) = create_scene(entity_count);
+        group.bench_with_input(BenchmarkId::new("table", entity_count), &ids, |bencher, ids| {
+            bencher.iter(|| {
+                for id in ids.iter() {
+                    scene.add_component(*id, TableEffect { strength: 1.0 });
+                }
+                for id in ids.iter() {
+                    scene.remove_component::<TableEffect>(*id);
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("sparse_set", entity_count), &ids, |bencher, ids| {
+            bencher.iter(|| {
+                for id in ids.iter() {
+                    scene.add_component(*id, SparseEffect { strength: 1.0 });
+                }
+                for id in ids.iter() {
+                    scene.remove_component::<SparseEffect>(*id);
+                }
+            })
+        });
+        let mut storage = MapStorage::new(entity_count);
+        group.bench_with_input(
+            BenchmarkId::new("hash_map", entity_count),
+            &entity_count,
+            |bencher, entity_count| {
+                bencher.iter(|| {
+                    for entity in 0..*entity_count {
+                        storage.add(entity, TableEffect { strength: 1.0 });
+                    }
+                    for entity in 0..*entity_count {
+                        storage.remove::<TableEffect>(entity);
+                    }
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+// Sparse set components are looked up entity by entity, so iterating them is slower
+fn iterate(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("storage_iterate");
+    for entity_count in [1_000, 10_000] {
+        let (mut scene, ids) = create_scene(entity_count);
+        for id in ids.iter() {
+            scene.add_component(*id, TableEffect { strength: 1.0 });
+            scene.add_component(*id, SparseEffect { strength: 1.0 });
+        }
+        group.bench_function(BenchmarkId::new("table", entity_count), |bencher| {
+            bencher.iter(|| {
+                let mut sum = 0.0;
+                for (position, effect) in scene.query::<(&Position, &TableEffect)>() {
+                    sum += position.x * effect.strength;
+                }
+                black_box(sum)
+            })
+        });
+        group.bench_function(BenchmarkId::new("sparse_set", entity_count), |bencher| {
+            bencher.iter(|| {
+                let mut sum = 0.0;
+                for (position, effect) in scene.query::<(&Position, &SparseEffect)>() {
+                    sum += position.x * effect.strength;
+                }
+                black_box(sum)
+            })
+        });
+        group.bench_function(BenchmarkId::new("table_only", entity_count), |bencher| {
+            bencher.iter(|| {
+                let mut sum = 0.0;
+                for (position, velocity) in scene.query::<(&Position, &Velocity)>() {
+                    sum += position.x * velocity.x;
+                }
+                black_box(sum)
+            })
+        });
+        let mut storage = MapStorage::new(entity_count);
+        for entity in 0..entity_count {
+            storage.add(entity, TableEffect { strength: 1.0 });
+        }
+        group.bench_function(BenchmarkId::new("hash_map", entity_count), |bencher| {
+            bencher.iter(|| black_box(storage.sum(|position: &Position, effect: &TableEffect| position.x * effect.strength)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, add_remove, iterate);
+criterion_main!(benches);
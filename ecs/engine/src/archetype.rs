@@ -0,0 +1,334 @@
+use crate::command::Commands;
+use crate::entity::Component;
+use crate::entity::EntityId;
+use crate::entity::UpdateContext;
+use crate::resource::Resources;
+use std::any::type_name;
+use std::any::Any;
+use std::any::TypeId;
+use std::cell::UnsafeCell;
+use std::collections::HashMap;
+
+// Change ticks of one component. A tick is newer than the last run of a system if it is greater.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ComponentTicks {
+    added: u64,
+    changed: u64,
+}
+
+impl ComponentTicks {
+    pub(crate) fn new(tick: u64) -> ComponentTicks {
+        ComponentTicks { added: tick, changed: tick }
+    }
+
+    pub fn get_added(&self) -> u64 {
+        self.added
+    }
+
+    pub fn get_changed(&self) -> u64 {
+        self.changed
+    }
+
+    pub fn is_added(&self, last_run: u64) -> bool {
+        self.added > last_run
+    }
+
+    pub fn is_changed(&self, last_run: u64) -> bool {
+        self.changed > last_run
+    }
+
+    pub(crate) fn set_changed(&mut self, tick: u64) {
+        self.changed = tick;
+    }
+}
+
+pub(crate) trait ComponentColumn: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn get_type_name(&self) -> &'static str;
+    fn new_empty(&self) -> Box<dyn ComponentColumn>;
+    fn move_row(&mut self, row: usize, target: &mut dyn ComponentColumn);
+    fn remove_row(&mut self, row: usize);
+    fn reset_ticks(&mut self, row: usize, tick: u64);
+    fn update(&mut self, entities: &[EntityId], resources: &Resources, commands: &mut Commands, tick: u64);
+    fn on_entity_destroyed(&mut self, row: usize, entity: EntityId);
+}
+
+pub(crate) struct Column<T> {
+    values: Vec<T>,
+    ticks: Vec<ComponentTicks>,
+}
+
+impl<T: Component + 'static> Column<T> {
+    fn new() -> Column<T> {
+        Column { values: vec![], ticks: vec![] }
+    }
+
+    fn push(&mut self, value: T, ticks: ComponentTicks) {
+        self.values.push(value);
+        self.ticks.push(ticks);
+    }
+
+    fn swap_remove(&mut self, row: usize) -> (T, ComponentTicks) {
+        (self.values.swap_remove(row), self.ticks.swap_remove(row))
+    }
+}
+
+impl<T: Component + 'static> ComponentColumn for Column<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn get_type_name(&self) -> &'static str {
+        type_name::<T>()
+    }
+
+    fn new_empty(&self) -> Box<dyn ComponentColumn> {
+        Box::new(Column::<T>::new())
+    }
+
+    fn move_row(&mut self, row: usize, target: &mut dyn ComponentColumn) {
+        let target = target.as_any_mut().downcast_mut::<Column<T>>().unwrap();
+        let (value, ticks) = self.swap_remove(row);
+        target.push(value, ticks);
+    }
+
+    fn remove_row(&mut self, row: usize) {
+        self.swap_remove(row);
+    }
+
+    fn reset_ticks(&mut self, row: usize, tick: u64) {
+        self.ticks[row] = ComponentTicks::new(tick);
+    }
+
+    fn update(&mut self, entities: &[EntityId], resources: &Resources, commands: &mut Commands, tick: u64) {
+        let rows = self.values.iter_mut().zip(self.ticks.iter_mut());
+        for ((component, ticks), entity) in rows.zip(entities.iter()) {
+            let mut context = UpdateContext::new(*entity, resources, commands);
+            component.update(&mut context);
+            if context.is_changed() {
+                ticks.set_changed(tick);
+            }
+        }
+    }
+
+    fn on_entity_destroyed(&mut self, row: usize, entity: EntityId) {
+        self.values[row].on_entity_destroyed(entity);
+        self.values[row].on_remove(entity);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) struct EntityLocation {
+    pub archetype: usize,
+    pub row: usize,
+}
+
+pub struct Archetype {
+    component_types: Vec<TypeId>,
+    // Queries hand out mutable access to several columns of the same archetype at once
+    columns: Vec<UnsafeCell<Box<dyn ComponentColumn>>>,
+    entities: Vec<EntityId>,
+    add_edges: HashMap<TypeId, usize>,
+    remove_edges: HashMap<TypeId, usize>,
+}
+
+// Columns are only written through a shared reference by systems whose declared
+// access the schedule has checked not to conflict with any concurrently running system.
+unsafe impl Sync for Archetype {}
+
+impl Archetype {
+    pub(crate) fn empty() -> Archetype {
+        Archetype::new(vec![])
+    }
+
+    // Columns must be sorted by type id
+    fn new(columns: Vec<(TypeId, Box<dyn ComponentColumn>)>) -> Archetype {
+        let (component_types, columns) = columns
+            .into_iter()
+            .map(|(type_id, column)| (type_id, UnsafeCell::new(column)))
+            .unzip();
+        Archetype {
+            component_types,
+            columns,
+            entities: vec![],
+            add_edges: HashMap::new(),
+            remove_edges: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn with_added<T: Component + 'static>(&self) -> Archetype {
+        let mut columns = self.empty_columns();
+        columns.push((TypeId::of::<T>(), Box::new(Column::<T>::new())));
+        columns.sort_by_key(|(type_id, _)| *type_id);
+        Archetype::new(columns)
+    }
+
+    pub(crate) fn with_removed(&self, removed: TypeId) -> Archetype {
+        let mut columns = self.empty_columns();
+        columns.retain(|(type_id, _)| *type_id != removed);
+        Archetype::new(columns)
+    }
+
+    // An archetype with the same component types and no entities
+    pub(crate) fn empty_copy(&self) -> Archetype {
+        Archetype::new(self.empty_columns())
+    }
+
+    fn empty_columns(&self) -> Vec<(TypeId, Box<dyn ComponentColumn>)> {
+        self.component_types
+            .iter()
+            .zip(self.columns.iter())
+            .map(|(type_id, column)| (*type_id, unsafe { &*column.get() }.new_empty()))
+            .collect()
+    }
+
+    pub fn get_component_types(&self) -> &[TypeId] {
+        &self.component_types
+    }
+
+    pub fn get_component_type_names(&self) -> Vec<&'static str> {
+        self.columns.iter().map(|x| unsafe { &*x.get() }.get_type_name()).collect()
+    }
+
+    pub fn get_entities(&self) -> &[EntityId] {
+        &self.entities
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    pub fn has_component(&self, type_id: TypeId) -> bool {
+        self.column_index(type_id).is_some()
+    }
+
+    fn get_typed_column<T: Component + 'static>(&self) -> Option<&Column<T>> {
+        let index = self.column_index(TypeId::of::<T>())?;
+        let column = unsafe { &*self.columns[index].get() };
+        Some(column.as_any().downcast_ref::<Column<T>>().unwrap())
+    }
+
+    fn get_typed_column_mut<T: Component + 'static>(&mut self) -> Option<&mut Column<T>> {
+        let index = self.column_index(TypeId::of::<T>())?;
+        let column = self.columns[index].get_mut();
+        Some(column.as_any_mut().downcast_mut::<Column<T>>().unwrap())
+    }
+
+    pub fn get_column<T: Component + 'static>(&self) -> Option<&[T]> {
+        Some(&self.get_typed_column::<T>()?.values)
+    }
+
+    pub fn get_ticks<T: Component + 'static>(&self) -> Option<&[ComponentTicks]> {
+        Some(&self.get_typed_column::<T>()?.ticks)
+    }
+
+    // Mutable access to the components and their ticks. Callers are expected to mark what they change.
+    pub(crate) fn get_column_mut<T: Component + 'static>(&mut self) -> Option<(&mut [T], &mut [ComponentTicks])> {
+        let column = self.get_typed_column_mut::<T>()?;
+        Some((&mut column.values, &mut column.ticks))
+    }
+
+    // The caller must hold the access to the column that it uses the returned pointers for.
+    // Only a shared borrow of the column is taken, so readers on several threads never alias a mutable one,
+    // and writes go to the buffers the vectors own.
+    pub(crate) fn get_column_ptr<T: Component + 'static>(&self) -> Option<(*mut T, *mut ComponentTicks)> {
+        let column = self.get_typed_column::<T>()?;
+        Some((column.values.as_ptr() as *mut T, column.ticks.as_ptr() as *mut ComponentTicks))
+    }
+
+    // Components are marked as changed with the tick only if their update calls UpdateContext::set_changed
+    pub(crate) fn update(&mut self, resources: &Resources, commands: &mut Commands, tick: u64) {
+        for column in self.columns.iter_mut() {
+            column.get_mut().update(&self.entities, resources, commands, tick);
+        }
+    }
+
+    pub(crate) fn get_add_edge(&self, type_id: TypeId) -> Option<usize> {
+        self.add_edges.get(&type_id).copied()
+    }
+
+    pub(crate) fn get_remove_edge(&self, type_id: TypeId) -> Option<usize> {
+        self.remove_edges.get(&type_id).copied()
+    }
+
+    pub(crate) fn set_add_edge(&mut self, type_id: TypeId, archetype: usize) {
+        self.add_edges.insert(type_id, archetype);
+    }
+
+    pub(crate) fn set_remove_edge(&mut self, type_id: TypeId, archetype: usize) {
+        self.remove_edges.insert(type_id, archetype);
+    }
+
+    fn column_index(&self, type_id: TypeId) -> Option<usize> {
+        self.component_types.binary_search(&type_id).ok()
+    }
+
+    pub(crate) fn push_entity(&mut self, id: EntityId) -> usize {
+        self.entities.push(id);
+        self.entities.len() - 1
+    }
+
+    pub(crate) fn push_component<T: Component + 'static>(&mut self, component: T, tick: u64) {
+        let column = self.get_typed_column_mut::<T>().unwrap();
+        column.push(component, ComponentTicks::new(tick));
+    }
+
+    pub(crate) fn on_entity_destroyed(&mut self, row: usize, entity: EntityId) {
+        for column in self.columns.iter_mut() {
+            column.get_mut().on_entity_destroyed(row, entity);
+        }
+    }
+
+    // Removes the row by swapping the last row into its place.
+    // Returns the entity that now lives in the row, if any.
+    pub(crate) fn remove_row(&mut self, row: usize) -> Option<EntityId> {
+        for column in self.columns.iter_mut() {
+            column.get_mut().remove_row(row);
+        }
+        self.remove_entity(row)
+    }
+
+    // Moves all components that exist in both archetypes to the target and
+    // returns the removed component of type T if this archetype had one.
+    pub(crate) fn move_row<T: Component + 'static>(&mut self, row: usize, target: &mut Archetype) -> (Option<T>, Option<EntityId>) {
+        let mut removed = None;
+        for (type_id, column) in self.component_types.iter().zip(self.columns.iter_mut()) {
+            let column = column.get_mut();
+            match target.column_index(*type_id) {
+                Some(index) => column.move_row(row, target.columns[index].get_mut().as_mut()),
+                None if *type_id == TypeId::of::<T>() => {
+                    removed = Some(column.as_any_mut().downcast_mut::<Column<T>>().unwrap().swap_remove(row).0);
+                }
+                None => column.remove_row(row),
+            }
+        }
+        (removed, self.remove_entity(row))
+    }
+
+    // Moves the row to an archetype of another scene with the same component types, where the
+    // components count as added at the given tick. Returns the new row and the entity swapped into the old one.
+    pub(crate) fn transfer_row(&mut self, row: usize, target: &mut Archetype, id: EntityId, tick: u64) -> (usize, Option<EntityId>) {
+        let target_row = target.entities.len();
+        for (column, target_column) in self.columns.iter_mut().zip(target.columns.iter_mut()) {
+            let target_column = target_column.get_mut();
+            column.get_mut().move_row(row, target_column.as_mut());
+            target_column.reset_ticks(target_row, tick);
+        }
+        target.push_entity(id);
+        (target_row, self.remove_entity(row))
+    }
+
+    fn remove_entity(&mut self, row: usize) -> Option<EntityId> {
+        self.entities.swap_remove(row);
+        self.entities.get(row).copied()
+    }
+}
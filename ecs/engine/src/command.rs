@@ -0,0 +1,206 @@
+use crate::entity::Component;
+use crate::entity::EntityId;
+use crate::relation::Relation;
+use crate::scene::Scene;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+static NEXT_QUEUE_ID: AtomicU64 = AtomicU64::new(1);
+
+fn next_queue_id() -> u64 {
+    NEXT_QUEUE_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+// An entity that a command queue will spawn. It only refers to that entity within the queue
+// and until the queue is applied.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SpawnedEntity {
+    queue: u64,
+    index: usize,
+}
+
+// Refers to an existing entity or to one spawned earlier by the same command queue
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CommandTarget {
+    Entity(EntityId),
+    Spawned(SpawnedEntity),
+}
+
+impl From<EntityId> for CommandTarget {
+    fn from(id: EntityId) -> CommandTarget {
+        CommandTarget::Entity(id)
+    }
+}
+
+impl CommandTarget {
+    // Returns None for entities spawned by another queue or by an earlier application of this one
+    fn resolve(self, spawned: &Spawned) -> Option<EntityId> {
+        match self {
+            CommandTarget::Entity(id) => Some(id),
+            CommandTarget::Spawned(entity) if entity.queue == spawned.queue => spawned.ids.get(entity.index).copied(),
+            CommandTarget::Spawned(_) => None,
+        }
+    }
+}
+
+// The entities spawned so far while a queue is applied
+struct Spawned {
+    queue: u64,
+    ids: Vec<EntityId>,
+}
+
+type Command = Box<dyn FnOnce(&mut Scene, &mut Spawned) + Send + Sync>;
+
+// Structural changes recorded while the scene is borrowed and applied later in the order they were pushed.
+// Commands on entities that no longer exist when the queue is applied do nothing.
+pub struct Commands {
+    commands: Vec<Command>,
+    spawn_count: usize,
+    queue: u64,
+}
+
+impl Default for Commands {
+    fn default() -> Commands {
+        Commands::new()
+    }
+}
+
+impl Commands {
+    pub fn new() -> Commands {
+        Commands { commands: vec![], spawn_count: 0, queue: next_queue_id() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn spawn(&mut self) -> EntityCommands<'_> {
+        self.push_spawn(None)
+    }
+
+    pub fn spawn_with_name(&mut self, name: String) -> EntityCommands<'_> {
+        self.push_spawn(Some(name))
+    }
+
+    pub fn entity<T: Into<CommandTarget>>(&mut self, target: T) -> EntityCommands<'_> {
+        EntityCommands { commands: self, target: target.into() }
+    }
+
+    pub fn despawn<T: Into<CommandTarget>>(&mut self, target: T) {
+        self.entity(target).despawn();
+    }
+
+    fn push_spawn(&mut self, name: Option<String>) -> EntityCommands<'_> {
+        self.push(move |scene, spawned| {
+            let id = match name {
+                Some(name) => scene.create_entity_with_name(name),
+                None => scene.create_entity(),
+            };
+            spawned.ids.push(id);
+        });
+        self.spawn_count += 1;
+        let target = CommandTarget::Spawned(SpawnedEntity { queue: self.queue, index: self.spawn_count - 1 });
+        EntityCommands { commands: self, target }
+    }
+
+    fn push<F: FnOnce(&mut Scene, &mut Spawned) + Send + Sync + 'static>(&mut self, command: F) {
+        self.commands.push(Box::new(command));
+    }
+
+    // The queue gets a new id so that the handles of the applied spawns can not refer to later ones
+    pub(crate) fn apply(&mut self, scene: &mut Scene) {
+        let mut spawned = Spawned { queue: self.queue, ids: Vec::with_capacity(self.spawn_count) };
+        for command in self.commands.drain(..) {
+            command(scene, &mut spawned);
+        }
+        self.spawn_count = 0;
+        self.queue = next_queue_id();
+    }
+}
+
+pub struct EntityCommands<'a> {
+    commands: &'a mut Commands,
+    target: CommandTarget,
+}
+
+impl<'a> EntityCommands<'a> {
+    pub fn get_target(&self) -> CommandTarget {
+        self.target
+    }
+
+    pub fn add_component<T: Component + 'static>(self, component: T) -> EntityCommands<'a> {
+        let target = self.target;
+        self.commands.push(move |scene, spawned| {
+            if let Some(id) = target.resolve(spawned) {
+                scene.add_component(id, component);
+            }
+        });
+        self
+    }
+
+    pub fn remove_component<T: Component + 'static>(self) -> EntityCommands<'a> {
+        let target = self.target;
+        self.commands.push(move |scene, spawned| {
+            if let Some(id) = target.resolve(spawned) {
+                scene.remove_component::<T>(id);
+            }
+        });
+        self
+    }
+
+    pub fn set_parent<T: Into<CommandTarget>>(self, parent: T) -> EntityCommands<'a> {
+        let target = self.target;
+        let parent = parent.into();
+        self.commands.push(move |scene, spawned| {
+            if let (Some(id), Some(parent)) = (target.resolve(spawned), parent.resolve(spawned)) {
+                scene.set_parent(id, parent);
+            }
+        });
+        self
+    }
+
+    pub fn remove_parent(self) -> EntityCommands<'a> {
+        let target = self.target;
+        self.commands.push(move |scene, spawned| {
+            if let Some(id) = target.resolve(spawned) {
+                scene.remove_parent(id);
+            }
+        });
+        self
+    }
+
+    pub fn add_relation<R: Relation, T: Into<CommandTarget>>(self, target: T) -> EntityCommands<'a> {
+        let source = self.target;
+        let target = target.into();
+        self.commands.push(move |scene, spawned| {
+            if let (Some(source), Some(target)) = (source.resolve(spawned), target.resolve(spawned)) {
+                scene.add_relation::<R>(source, target);
+            }
+        });
+        self
+    }
+
+    pub fn remove_relation<R: Relation, T: Into<CommandTarget>>(self, target: T) -> EntityCommands<'a> {
+        let source = self.target;
+        let target = target.into();
+        self.commands.push(move |scene, spawned| {
+            if let (Some(source), Some(target)) = (source.resolve(spawned), target.resolve(spawned)) {
+                scene.remove_relation::<R>(source, target);
+            }
+        });
+        self
+    }
+
+    pub fn despawn(self) {
+        let target = self.target;
+        self.commands.push(move |scene, spawned| {
+            if let Some(id) = target.resolve(spawned) {
+                scene.destroy_entity(id);
+            }
+        });
+    }
+}
@@ -1,26 +1,35 @@
+use crate::reflect::Reflect;
+use serde::Deserialize;
+use serde::Serialize;
+
+#[derive(Clone, Serialize, Deserialize, Reflect)]
 pub struct CountComponent {
-    count: std::cell::Cell<u64>,
+    count: u64,
+}
+
+impl Default for CountComponent {
+    fn default() -> CountComponent {
+        CountComponent::new()
+    }
 }
 
 impl CountComponent {
     pub fn new() -> CountComponent {
-        CountComponent {
-            count: std::cell::Cell::new(0),
-        }
+        CountComponent { count: 0 }
     }
 
     pub fn get_count(&self) -> u64 {
-        self.count.get()
+        self.count
     }
 
     pub fn override_count(&mut self, count: u64) {
-        self.count.set(count);
+        self.count = count;
     }
 }
 
 impl crate::entity::Component for CountComponent {
-    fn update(&self) {
-        self.count.set(self.count.get() + 1);
+    fn update(&mut self, _context: &mut crate::entity::UpdateContext) {
+        self.count += 1;
     }
 
     fn as_any(&self) -> &dyn std::any::Any {
@@ -1,101 +1,136 @@
+use crate::command::Commands;
+use crate::resource::Resources;
+use crate::storage::StorageType;
+use serde::Deserialize;
+use serde::Serialize;
 use std::any::Any;
-use std::any::TypeId;
-use std::cell::RefCell;
-use std::collections::hash_map::Entry;
 use std::collections::HashMap;
-use std::option::Option;
-use std::rc::Rc;
 
-pub trait Component {
-    fn update(&self);
-    fn as_any(&self) -> &dyn Any;
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct EntityId {
+    index: u32,
+    generation: u32,
 }
 
-pub struct Entity {
-    id: u64,
-    name: String,
-    valid: bool,
-    components: HashMap<TypeId, Vec<Rc<RefCell<dyn Component>>>>,
+impl EntityId {
+    pub(crate) fn new(index: u32, generation: u32) -> EntityId {
+        EntityId { index, generation }
+    }
+
+    pub fn get_index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn get_generation(&self) -> u32 {
+        self.generation
+    }
 }
 
-impl Entity {
-    pub(crate) fn new(id: u64, name: String) -> Entity {
-        Entity {
-            id: id,
-            name: name,
-            valid: true,
-            components: HashMap::new(),
-        }
+// The ids entities had in the scene they were moved from, mapped to their ids in the scene they were moved to
+#[derive(Clone, Default, Debug)]
+pub struct EntityMap {
+    ids: HashMap<EntityId, EntityId>,
+}
+
+impl EntityMap {
+    pub fn new() -> EntityMap {
+        EntityMap::default()
     }
 
-    pub(crate) fn update(&self) {
-        for (_, vec) in self.components.iter() {
-            for comp in vec.iter() {
-                comp.borrow().update()
-            }
-        }
+    pub fn get(&self, id: EntityId) -> Option<EntityId> {
+        self.ids.get(&id).copied()
     }
 
-    pub fn get_id(&self) -> u64 {
-        self.id
+    pub fn len(&self) -> usize {
+        self.ids.len()
     }
 
-    pub fn get_name(&self) -> String {
-        self.name.clone()
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
     }
 
-    pub fn set_name(&mut self, name: String) {
-        self.name = name;
+    // Pairs of old and new ids in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, EntityId)> + '_ {
+        self.ids.iter().map(|(old, new)| (*old, *new))
     }
 
-    pub(crate) fn invalidate(&mut self) {
-        self.valid = false;
+    pub(crate) fn insert(&mut self, old: EntityId, new: EntityId) {
+        self.ids.insert(old, new);
     }
+}
+
+pub trait Component: Send + Sync {
+    // Components that are added and removed often should use StorageType::SparseSet
+    const STORAGE: StorageType = StorageType::Table;
 
-    pub fn is_valid(&self) -> bool {
-        self.valid
+    fn update(&mut self, _context: &mut UpdateContext) {}
+    fn as_any(&self) -> &dyn Any;
+
+    // Called after the component is attached to an entity
+    fn on_add(&mut self, _entity: EntityId) {}
+
+    // Called when the component is detached, replaced or its entity is destroyed
+    fn on_remove(&mut self, _entity: EntityId) {}
+
+    // Called before on_remove when the entity is destroyed through Scene::destroy_entity
+    fn on_entity_destroyed(&mut self, _entity: EntityId) {}
+}
+
+// Passed to Component::update. Structural changes go through the commands,
+// which the scene applies after all components are updated.
+pub struct UpdateContext<'a> {
+    entity: EntityId,
+    resources: &'a Resources,
+    commands: &'a mut Commands,
+    changed: bool,
+}
+
+impl<'a> UpdateContext<'a> {
+    pub(crate) fn new(entity: EntityId, resources: &'a Resources, commands: &'a mut Commands) -> UpdateContext<'a> {
+        UpdateContext { entity, resources, commands, changed: false }
     }
 
-    pub fn add_component<T: Component + 'static>(&mut self, new_component: T) {
-        let type_id = TypeId::of::<T>();
-        let entry = self.components.entry(type_id).or_insert(vec![]);
-        entry.push(Rc::new(RefCell::new(new_component)));
+    pub fn get_entity(&self) -> EntityId {
+        self.entity
     }
 
-    pub fn get_component<T: Component + 'static>(&mut self) -> Option<Rc<RefCell<dyn Component>>> {
-        let type_id = TypeId::of::<T>();
-        let entry = self.components.entry(type_id);
-        match entry {
-            Entry::Occupied(e) => match e.get().first() {
-                Some(c) => Option::Some(c.clone()),
-                None => Option::None,
-            },
-            Entry::Vacant(_) => Option::None,
-        }
+    pub fn get_resources(&self) -> &Resources {
+        self.resources
     }
 
-    pub fn get_raw_component<T: Component + 'static>(&mut self) -> Option<*mut T> {
-        let type_id = TypeId::of::<T>();
-        let entry = self.components.entry(type_id);
-        match entry {
-            Entry::Occupied(e) => match e.get().first() {
-                Some(c) => Option::Some((*c).as_ptr() as *mut T),
-                None => Option::None,
-            },
-            Entry::Vacant(_) => Option::None,
-        }
+    pub fn get_commands(&mut self) -> &mut Commands {
+        self.commands
     }
 
-    // Todo: should be moved to component module
-    pub fn component_as<T: Component + 'static>(comp: &Rc<RefCell<dyn Component>>) -> &mut T {
-        let ptr = (*comp).as_ptr() as *mut T;
-        unsafe { &mut *ptr }
+    // Component updates are not tracked by change detection unless they mark the component as changed
+    pub fn set_changed(&mut self) {
+        self.changed = true;
     }
 
-    pub fn get_components<T: Component + 'static>(
-        &mut self,
-    ) -> Option<&Vec<Rc<RefCell<dyn Component>>>> {
-        self.components.get(&TypeId::of::<T>())
+    pub(crate) fn is_changed(&self) -> bool {
+        self.changed
+    }
+}
+
+pub struct Entity {
+    id: EntityId,
+    name: String,
+}
+
+impl Entity {
+    pub(crate) fn new(id: EntityId, name: String) -> Entity {
+        Entity { id, name }
+    }
+
+    pub fn get_id(&self) -> EntityId {
+        self.id
+    }
+
+    pub fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub(crate) fn set_name(&mut self, name: String) {
+        self.name = name;
     }
-    // Todo: remove_component
 }
@@ -0,0 +1,168 @@
+use crate::entity::Component;
+use crate::entity::EntityId;
+use crate::scene::Scene;
+use std::any::type_name;
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::ops::Deref;
+use std::ops::DerefMut;
+
+pub struct EntityRef<'a> {
+    scene: &'a Scene,
+    id: EntityId,
+}
+
+impl<'a> EntityRef<'a> {
+    pub(crate) fn new(scene: &'a Scene, id: EntityId) -> EntityRef<'a> {
+        EntityRef { scene, id }
+    }
+
+    pub fn get_id(&self) -> EntityId {
+        self.id
+    }
+
+    pub fn get_name(&self) -> String {
+        self.scene.get_entity_by_id(self.id).unwrap().get_name()
+    }
+
+    pub fn has<T: Component + 'static>(&self) -> bool {
+        self.scene.has_component::<T>(self.id)
+    }
+
+    pub fn get<T: Component + 'static>(&self) -> Option<&'a T> {
+        self.scene.get_component::<T>(self.id)
+    }
+}
+
+// Borrow state per component type: positive for shared borrows, -1 for a mutable borrow
+type BorrowTable = RefCell<Vec<(TypeId, isize)>>;
+
+pub struct EntityMut<'a> {
+    scene: &'a mut Scene,
+    id: EntityId,
+    borrows: BorrowTable,
+}
+
+impl<'a> EntityMut<'a> {
+    pub(crate) fn new(scene: &'a mut Scene, id: EntityId) -> EntityMut<'a> {
+        EntityMut { scene, id, borrows: RefCell::new(vec![]) }
+    }
+
+    pub fn get_id(&self) -> EntityId {
+        self.id
+    }
+
+    pub fn get_name(&self) -> String {
+        self.scene.get_entity_by_id(self.id).unwrap().get_name()
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        self.scene.set_entity_name(self.id, name);
+    }
+
+    pub fn has<T: Component + 'static>(&self) -> bool {
+        self.scene.has_component::<T>(self.id)
+    }
+
+    // Panics if T is already borrowed mutably through this entity
+    pub fn get<T: Component + 'static>(&self) -> Option<Ref<'_, T>> {
+        if !self.has::<T>() {
+            return None;
+        }
+        let borrow = BorrowGuard::new::<T>(&self.borrows, false);
+        let value = self.scene.get_component::<T>(self.id).unwrap();
+        Some(Ref { value, _borrow: borrow })
+    }
+
+    // Panics if T is already borrowed through this entity
+    pub fn get_mut<T: Component + 'static>(&self) -> Option<RefMut<'_, T>> {
+        if !self.has::<T>() {
+            return None;
+        }
+        let borrow = BorrowGuard::new::<T>(&self.borrows, true);
+        // The scene is borrowed exclusively by this entity and the guard makes this the only borrow of T
+        let tick = self.scene.get_change_tick();
+        let value = unsafe { self.scene.get_component_unchecked_mut::<T>(self.id, tick) }.unwrap();
+        Some(RefMut { value, _borrow: borrow })
+    }
+
+    pub fn add_component<T: Component + 'static>(&mut self, component: T) {
+        self.scene.add_component(self.id, component);
+    }
+
+    pub fn remove_component<T: Component + 'static>(&mut self) -> bool {
+        self.scene.remove_component::<T>(self.id)
+    }
+
+    pub fn take_component<T: Component + 'static>(&mut self) -> Option<T> {
+        self.scene.take_component::<T>(self.id)
+    }
+}
+
+struct BorrowGuard<'a> {
+    borrows: &'a BorrowTable,
+    type_id: TypeId,
+}
+
+impl<'a> BorrowGuard<'a> {
+    fn new<T: 'static>(borrows: &'a BorrowTable, mutable: bool) -> BorrowGuard<'a> {
+        let type_id = TypeId::of::<T>();
+        let mut table = borrows.borrow_mut();
+        let index = match table.iter().position(|(x, _)| *x == type_id) {
+            Some(index) => index,
+            None => {
+                table.push((type_id, 0));
+                table.len() - 1
+            }
+        };
+        let state = &mut table[index].1;
+        if *state < 0 {
+            panic!("{} is already borrowed mutably", type_name::<T>());
+        }
+        if mutable && *state > 0 {
+            panic!("{} is already borrowed", type_name::<T>());
+        }
+        *state = if mutable { -1 } else { *state + 1 };
+        BorrowGuard { borrows, type_id }
+    }
+}
+
+impl<'a> Drop for BorrowGuard<'a> {
+    fn drop(&mut self) {
+        let mut table = self.borrows.borrow_mut();
+        let state = &mut table.iter_mut().find(|(x, _)| *x == self.type_id).unwrap().1;
+        *state = if *state < 0 { 0 } else { *state - 1 };
+    }
+}
+
+pub struct Ref<'a, T> {
+    value: &'a T,
+    _borrow: BorrowGuard<'a>,
+}
+
+impl<'a, T> Deref for Ref<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+pub struct RefMut<'a, T> {
+    value: &'a mut T,
+    _borrow: BorrowGuard<'a>,
+}
+
+impl<'a, T> Deref for RefMut<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<'a, T> DerefMut for RefMut<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.value
+    }
+}
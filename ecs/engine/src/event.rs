@@ -0,0 +1,114 @@
+use crate::resource::Resources;
+use std::marker::PhantomData;
+
+struct EventBuffer<E> {
+    // Number of events sent before the first event of this buffer
+    start: usize,
+    events: Vec<E>,
+}
+
+impl<E> EventBuffer<E> {
+    fn new(start: usize) -> EventBuffer<E> {
+        EventBuffer { start, events: vec![] }
+    }
+
+    // Returns the events that were sent as the given event number or later
+    fn get_from(&self, event: usize) -> &[E] {
+        let skipped = event.saturating_sub(self.start).min(self.events.len());
+        &self.events[skipped..]
+    }
+}
+
+// Double buffered queue of events of one type, stored as a resource.
+// Events stay readable during the frame they were sent in and the frame after.
+pub struct Events<E> {
+    previous: EventBuffer<E>,
+    current: EventBuffer<E>,
+    event_count: usize,
+}
+
+impl<E> Default for Events<E> {
+    fn default() -> Events<E> {
+        Events::new()
+    }
+}
+
+impl<E> Events<E> {
+    pub fn new() -> Events<E> {
+        Events {
+            previous: EventBuffer::new(0),
+            current: EventBuffer::new(0),
+            event_count: 0,
+        }
+    }
+
+    pub fn send(&mut self, event: E) {
+        self.current.events.push(event);
+        self.event_count += 1;
+    }
+
+    // Returns the number of events sent since the queue was created
+    pub fn get_event_count(&self) -> usize {
+        self.event_count
+    }
+
+    pub fn len(&self) -> usize {
+        self.previous.events.len() + self.current.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Drops the events of the previous frame, called by Scene::update for every registered event type
+    pub fn update(&mut self) {
+        let current = std::mem::replace(&mut self.current, EventBuffer::new(self.event_count));
+        self.previous = current;
+    }
+}
+
+pub(crate) fn update_events<E: Send + Sync + 'static>(resources: &mut Resources) {
+    if let Some(events) = resources.get_mut::<Events<E>>() {
+        events.update();
+    }
+}
+
+pub struct EventWriter<'a, E> {
+    events: &'a mut Events<E>,
+}
+
+impl<'a, E> EventWriter<'a, E> {
+    pub fn new(events: &'a mut Events<E>) -> EventWriter<'a, E> {
+        EventWriter { events }
+    }
+
+    pub fn send(&mut self, event: E) {
+        self.events.send(event);
+    }
+}
+
+// Remembers which events were already read so every reader sees each event once
+pub struct EventReader<E> {
+    next_event: usize,
+    event_type: PhantomData<fn() -> E>,
+}
+
+impl<E> Default for EventReader<E> {
+    fn default() -> EventReader<E> {
+        EventReader::new()
+    }
+}
+
+impl<E> EventReader<E> {
+    pub fn new() -> EventReader<E> {
+        EventReader { next_event: 0, event_type: PhantomData }
+    }
+
+    // Returns the events sent since the last read that have not been dropped yet
+    pub fn read<'a>(&mut self, events: &'a Events<E>) -> impl Iterator<Item = &'a E> {
+        let previous = events.previous.get_from(self.next_event);
+        let current = events.current.get_from(self.next_event);
+        self.next_event = events.event_count;
+        previous.iter().chain(current.iter())
+    }
+}
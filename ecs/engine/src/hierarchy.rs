@@ -0,0 +1,51 @@
+use crate::entity::Component;
+use crate::entity::EntityId;
+
+// Managed by Scene::set_parent and Scene::remove_parent, together with Children on the parent
+pub struct Parent {
+    entity: EntityId,
+}
+
+impl Parent {
+    pub(crate) fn new(entity: EntityId) -> Parent {
+        Parent { entity }
+    }
+
+    pub fn get(&self) -> EntityId {
+        self.entity
+    }
+}
+
+impl Component for Parent {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+pub struct Children {
+    entities: Vec<EntityId>,
+}
+
+impl Children {
+    pub(crate) fn new() -> Children {
+        Children { entities: vec![] }
+    }
+
+    pub fn get(&self) -> &[EntityId] {
+        &self.entities
+    }
+
+    pub(crate) fn push(&mut self, entity: EntityId) {
+        self.entities.push(entity);
+    }
+
+    pub(crate) fn remove(&mut self, entity: EntityId) {
+        self.entities.retain(|x| *x != entity);
+    }
+}
+
+impl Component for Children {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
@@ -0,0 +1,339 @@
+use crate::entity::EntityId;
+use crate::hierarchy::Children;
+use crate::scene::Scene;
+use crate::serialization::ComponentCopy;
+use crate::snapshot::EntitySnapshot;
+use std::any::TypeId;
+
+type RemoveFn = fn(&mut Scene, EntityId) -> bool;
+
+// A destroyed or spawned entity tree with the place of its root among its siblings and the relations of all its entities
+pub(crate) struct DestroyedEntities {
+    entities: Vec<EntitySnapshot>,
+    siblings: Option<(EntityId, Vec<EntityId>)>,
+    relations: Vec<(TypeId, EntityId, EntityId)>,
+}
+
+// One reversible change to the entities of a scene. Components are kept as copies,
+// so only the components registered for cloning or serialization can be recorded.
+pub(crate) enum Edit {
+    CreateEntity {
+        id: EntityId,
+        name: String,
+    },
+    DestroyEntity(DestroyedEntities),
+    SpawnEntities(DestroyedEntities),
+    SetName {
+        id: EntityId,
+        old: String,
+        new: String,
+    },
+    AddComponent {
+        id: EntityId,
+        new: ComponentCopy,
+        remove: RemoveFn,
+    },
+    ReplaceComponent {
+        id: EntityId,
+        old: ComponentCopy,
+        new: ComponentCopy,
+    },
+    RemoveComponent {
+        id: EntityId,
+        old: ComponentCopy,
+        remove: RemoveFn,
+    },
+    SetParent {
+        child: EntityId,
+        old: Option<(EntityId, Vec<EntityId>)>,
+        new: Option<EntityId>,
+    },
+    SetRelation {
+        kind: TypeId,
+        source: EntityId,
+        target: EntityId,
+        added: bool,
+    },
+}
+
+impl Edit {
+    // Edits of entities that are gone because of unrecorded changes do nothing
+    fn undo(&self, scene: &mut Scene) {
+        match self {
+            Edit::CreateEntity { id, .. } => {
+                scene.destroy_entity(*id);
+            }
+            Edit::DestroyEntity(destroyed) => destroyed.restore(scene),
+            Edit::SpawnEntities(spawned) => {
+                scene.destroy_entity(spawned.entities[0].get_id());
+            }
+            Edit::SetName { id, old, .. } => {
+                scene.set_entity_name(*id, old.clone());
+            }
+            Edit::AddComponent { id, remove, .. } => {
+                remove(scene, *id);
+            }
+            Edit::ReplaceComponent { id, old, .. } | Edit::RemoveComponent { id, old, .. } => restore_component(scene, *id, old),
+            Edit::SetParent { child, old, .. } => {
+                scene.remove_parent(*child);
+                if let Some((parent, siblings)) = old {
+                    scene.set_parent(*child, *parent);
+                    set_children(scene, *parent, siblings);
+                }
+            }
+            Edit::SetRelation { kind, source, target, added } => set_relation(scene, *kind, *source, *target, !*added),
+        }
+    }
+
+    fn redo(&self, scene: &mut Scene) {
+        match self {
+            Edit::CreateEntity { id, name } => {
+                scene.restore_entity(*id, name.clone());
+            }
+            Edit::DestroyEntity(destroyed) => {
+                scene.destroy_entity(destroyed.entities[0].get_id());
+            }
+            Edit::SpawnEntities(spawned) => spawned.restore(scene),
+            Edit::SetName { id, new, .. } => {
+                scene.set_entity_name(*id, new.clone());
+            }
+            Edit::AddComponent { id, new, .. } | Edit::ReplaceComponent { id, new, .. } => restore_component(scene, *id, new),
+            Edit::RemoveComponent { id, remove, .. } => {
+                remove(scene, *id);
+            }
+            Edit::SetParent { child, new, .. } => {
+                match new {
+                    Some(parent) => scene.set_parent(*child, *parent),
+                    None => scene.remove_parent(*child),
+                };
+            }
+            Edit::SetRelation { kind, source, target, added } => set_relation(scene, *kind, *source, *target, *added),
+        }
+    }
+}
+
+impl DestroyedEntities {
+    // Returns None if a component of the tree can not be copied
+    pub(crate) fn take(scene: &Scene, id: EntityId) -> Option<DestroyedEntities> {
+        let ids = scene.get_tree(id);
+        let mut skipped = vec![];
+        let entities: Vec<EntitySnapshot> = ids.iter().map(|x| EntitySnapshot::take(scene, *x, &mut skipped)).collect();
+        if !skipped.is_empty() {
+            return None;
+        }
+        let siblings = scene.get_parent(id).map(|parent| (parent, scene.get_children(parent).to_vec()));
+        let mut relations = vec![];
+        for id in ids {
+            for pair in scene.get_relations().get_entity_pairs(id) {
+                if !relations.contains(&pair) {
+                    relations.push(pair);
+                }
+            }
+        }
+        Some(DestroyedEntities { entities, siblings, relations })
+    }
+
+    // All entities exist again before any of them gets its components and hierarchy links
+    fn restore(&self, scene: &mut Scene) {
+        for entity in self.entities.iter() {
+            scene.restore_entity(entity.get_id(), String::from(entity.get_name()));
+        }
+        for entity in self.entities.iter() {
+            entity.restore_components(scene);
+        }
+        if let Some((parent, siblings)) = &self.siblings {
+            set_children(scene, *parent, siblings);
+        }
+        for (kind, source, target) in self.relations.iter() {
+            scene.get_relations_mut().add(*kind, *source, *target);
+        }
+    }
+}
+
+fn restore_component(scene: &mut Scene, id: EntityId, component: &ComponentCopy) {
+    component
+        .restore(scene, id)
+        .expect("history components are checked to deserialize when they are copied");
+}
+
+// Puts the children of the parent back into their recorded order
+fn set_children(scene: &mut Scene, parent: EntityId, siblings: &[EntityId]) {
+    let mut children = Children::new();
+    for child in siblings.iter().filter(|x| scene.contains_entity(**x)) {
+        children.push(*child);
+    }
+    match scene.get_component_mut::<Children>(parent) {
+        Some(current) => *current = children,
+        None => {
+            scene.add_component(parent, children);
+        }
+    }
+}
+
+fn set_relation(scene: &mut Scene, kind: TypeId, source: EntityId, target: EntityId, added: bool) {
+    if added {
+        scene.get_relations_mut().add(kind, source, target);
+    } else {
+        scene.get_relations_mut().remove(kind, source, target);
+    }
+}
+
+// Returns None if the type is not registered for cloning or serialization or the entity has no such component
+pub(crate) fn copy_component(scene: &Scene, id: EntityId, type_id: TypeId) -> Option<ComponentCopy> {
+    scene.get_component_registry().copy_component(scene, id, type_id)?.ok()
+}
+
+// Edits that are undone and redone together
+pub(crate) struct Transaction {
+    name: String,
+    edits: Vec<Edit>,
+}
+
+impl Transaction {
+    fn new(name: &str) -> Transaction {
+        Transaction { name: String::from(name), edits: vec![] }
+    }
+
+    pub(crate) fn undo(&self, scene: &mut Scene) {
+        for edit in self.edits.iter().rev() {
+            edit.undo(scene);
+        }
+    }
+
+    pub(crate) fn redo(&self, scene: &mut Scene) {
+        for edit in self.edits.iter() {
+            edit.redo(scene);
+        }
+    }
+}
+
+// The undo and redo stacks of a scene. While recording, the entity edits made through the scene
+// (creating, destroying and renaming entities, spawning prefabs, adding and removing components, set_field_text,
+// parents and relations) are pushed as transactions of one edit, or into the open transaction.
+// Edits made by hooks and observers while an edit runs are not recorded since they run again on undo and redo.
+// Changes made by updates and through get_component_mut, queries, merges and loading are not recorded.
+// An edit that can not be reversed, because a component is not registered for cloning or serialization,
+// clears the history.
+#[derive(Default)]
+pub struct History {
+    recording: bool,
+    suspended: u32,
+    undo_stack: Vec<Transaction>,
+    redo_stack: Vec<Transaction>,
+    open: Option<Transaction>,
+    depth: u32,
+}
+
+impl History {
+    pub fn new() -> History {
+        History::default()
+    }
+
+    pub fn set_recording(&mut self, recording: bool) {
+        self.recording = recording;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording && self.suspended == 0
+    }
+
+    // Transactions can be nested, the edits go into the outermost one
+    pub fn begin_transaction(&mut self, name: &str) {
+        if self.depth == 0 {
+            self.open = Some(Transaction::new(name));
+        }
+        self.depth += 1;
+    }
+
+    // Panics if no transaction is open. Empty transactions are dropped.
+    pub fn end_transaction(&mut self) {
+        if self.depth == 0 {
+            panic!("no transaction is open");
+        }
+        self.depth -= 1;
+        if self.depth == 0 {
+            self.close_transaction();
+        }
+    }
+
+    fn close_transaction(&mut self) {
+        if let Some(transaction) = self.open.take() {
+            if !transaction.edits.is_empty() {
+                self.undo_stack.push(transaction);
+            }
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.get_undo_name().is_some()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    // The open transaction is undone first once it has edits
+    pub fn get_undo_name(&self) -> Option<&str> {
+        match &self.open {
+            Some(open) if !open.edits.is_empty() => Some(&open.name),
+            _ => self.undo_stack.last().map(|x| x.name.as_str()),
+        }
+    }
+
+    pub fn get_redo_name(&self) -> Option<&str> {
+        self.redo_stack.last().map(|x| x.name.as_str())
+    }
+
+    // Forgets all edits, including those of the open transaction
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        if let Some(open) = &mut self.open {
+            open.edits.clear();
+        }
+    }
+
+    pub(crate) fn suspend(&mut self) {
+        self.suspended += 1;
+    }
+
+    pub(crate) fn resume(&mut self) {
+        self.suspended -= 1;
+    }
+
+    // A new edit drops the edits that were undone. None stands for an edit that can not be reversed.
+    pub(crate) fn push(&mut self, name: &str, edit: Option<Edit>) {
+        let edit = match edit {
+            Some(edit) => edit,
+            None => return self.clear(),
+        };
+        self.redo_stack.clear();
+        match &mut self.open {
+            Some(open) => open.edits.push(edit),
+            None => self.undo_stack.push(Transaction { name: String::from(name), edits: vec![edit] }),
+        }
+    }
+
+    // The edits of the open transaction are undone as a whole. The transaction stays open for the edits that follow.
+    pub(crate) fn take_undo(&mut self) -> Option<Transaction> {
+        if let Some(open) = &mut self.open {
+            if !open.edits.is_empty() {
+                let edits = std::mem::take(&mut open.edits);
+                self.undo_stack.push(Transaction { name: open.name.clone(), edits });
+            }
+        }
+        self.undo_stack.pop()
+    }
+
+    pub(crate) fn take_redo(&mut self) -> Option<Transaction> {
+        self.redo_stack.pop()
+    }
+
+    pub(crate) fn push_undone(&mut self, transaction: Transaction) {
+        self.redo_stack.push(transaction);
+    }
+
+    pub(crate) fn push_redone(&mut self, transaction: Transaction) {
+        self.undo_stack.push(transaction);
+    }
+}
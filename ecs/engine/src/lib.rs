@@ -1,3 +1,26 @@
+// Lets #[derive(Reflect)] refer to the engine by name inside the engine itself
+extern crate self as engine;
+
+pub mod archetype;
+pub mod command;
+pub mod count_component;
 pub mod entity;
+pub mod entity_ref;
+pub mod event;
+pub mod hierarchy;
+pub mod history;
+pub mod observer;
+pub mod prefab;
+pub mod query;
+pub mod reflect;
+pub mod relation;
+pub mod resource;
+pub mod runner;
 pub mod scene;
-pub mod count_component;
\ No newline at end of file
+pub mod schedule;
+pub mod serialization;
+pub mod snapshot;
+pub mod state;
+pub mod storage;
+pub mod system;
+pub mod transform;
@@ -0,0 +1,94 @@
+use crate::entity::Component;
+use crate::entity::EntityId;
+use crate::scene::Scene;
+use std::any::TypeId;
+use std::marker::PhantomData;
+
+// The structural change an observer reacts to
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum TriggerKind {
+    Add(TypeId),
+    Remove(TypeId),
+    Despawn,
+}
+
+pub trait Trigger: 'static {
+    fn get_kind() -> TriggerKind;
+}
+
+// Fires after a component of type T is added to an entity, also when it replaces another one
+pub struct OnAdd<T>(PhantomData<T>);
+
+// Fires after a component of type T is removed, replaced or destroyed together with its entity
+pub struct OnRemove<T>(PhantomData<T>);
+
+// Fires after an entity is destroyed, once the id is no longer valid
+pub struct OnDespawn;
+
+impl<T: Component + 'static> Trigger for OnAdd<T> {
+    fn get_kind() -> TriggerKind {
+        TriggerKind::Add(TypeId::of::<T>())
+    }
+}
+
+impl<T: Component + 'static> Trigger for OnRemove<T> {
+    fn get_kind() -> TriggerKind {
+        TriggerKind::Remove(TypeId::of::<T>())
+    }
+}
+
+impl Trigger for OnDespawn {
+    fn get_kind() -> TriggerKind {
+        TriggerKind::Despawn
+    }
+}
+
+pub(crate) type ObserverFn = Box<dyn FnMut(EntityId, &mut Scene) + Send + Sync>;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ObserverId(u64);
+
+struct Observer {
+    id: ObserverId,
+    kind: TriggerKind,
+    // Taken out while the observer runs
+    callback: Option<ObserverFn>,
+}
+
+#[derive(Default)]
+pub(crate) struct Observers {
+    observers: Vec<Observer>,
+    next_id: u64,
+}
+
+impl Observers {
+    pub(crate) fn add(&mut self, kind: TriggerKind, callback: ObserverFn) -> ObserverId {
+        let id = ObserverId(self.next_id);
+        self.next_id += 1;
+        self.observers.push(Observer { id, kind, callback: Some(callback) });
+        id
+    }
+
+    pub(crate) fn remove(&mut self, id: ObserverId) -> bool {
+        let count = self.observers.len();
+        self.observers.retain(|x| x.id != id);
+        self.observers.len() < count
+    }
+
+    // Returns the observers of the kind in the order they were added
+    pub(crate) fn get_matching(&self, kind: TriggerKind) -> Vec<ObserverId> {
+        self.observers.iter().filter(|x| x.kind == kind).map(|x| x.id).collect()
+    }
+
+    // Returns None if the observer was removed or is already running
+    pub(crate) fn take(&mut self, id: ObserverId) -> Option<ObserverFn> {
+        self.observers.iter_mut().find(|x| x.id == id)?.callback.take()
+    }
+
+    // Drops the callback if the observer removed itself while it ran
+    pub(crate) fn put_back(&mut self, id: ObserverId, callback: ObserverFn) {
+        if let Some(observer) = self.observers.iter_mut().find(|x| x.id == id) {
+            observer.callback = Some(callback);
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use crate::entity::Component;
+use crate::entity::EntityId;
+use crate::scene::Scene;
+use crate::serialization;
+use crate::serialization::SceneError;
+use serde_json::Value;
+
+// Adds a copy of one prefab component to a spawned entity
+pub(crate) trait PrefabComponent: Send + Sync {
+    fn insert(&self, scene: &mut Scene, id: EntityId) -> Result<(), SceneError>;
+}
+
+struct ClonedComponent<T>(T);
+
+impl<T: Component + Clone + 'static> PrefabComponent for ClonedComponent<T> {
+    fn insert(&self, scene: &mut Scene, id: EntityId) -> Result<(), SceneError> {
+        scene.add_component(id, self.0.clone());
+        Ok(())
+    }
+}
+
+// A named entity with its components and child prefabs. Every spawn creates a new copy of the whole tree.
+pub struct Prefab {
+    name: String,
+    components: Vec<Box<dyn PrefabComponent>>,
+    children: Vec<Prefab>,
+}
+
+impl Prefab {
+    pub fn new(name: &str) -> Prefab {
+        Prefab {
+            name: String::from(name),
+            components: vec![],
+            children: vec![],
+        }
+    }
+
+    pub fn with_component<T: Component + Clone + 'static>(mut self, component: T) -> Prefab {
+        self.components.push(Box::new(ClonedComponent(component)));
+        self
+    }
+
+    pub fn with_child(mut self, child: Prefab) -> Prefab {
+        self.children.push(child);
+        self
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_children(&self) -> &[Prefab] {
+        &self.children
+    }
+
+    pub fn get_component_count(&self) -> usize {
+        self.components.len()
+    }
+
+    pub(crate) fn push_component(&mut self, component: Box<dyn PrefabComponent>) {
+        self.components.push(component);
+    }
+}
+
+struct FieldOverride {
+    path: String,
+    component: String,
+    field: String,
+    value: Value,
+}
+
+// Field values that replace the prefab's values in one spawned copy.
+// Components are named as in the component registry.
+#[derive(Default)]
+pub struct PrefabOverrides {
+    fields: Vec<FieldOverride>,
+}
+
+impl PrefabOverrides {
+    pub fn new() -> PrefabOverrides {
+        PrefabOverrides::default()
+    }
+
+    // The path is the names of the entities below the root separated by '/', or "" for the root itself.
+    // Children that share a name are told apart by their index among them, as in "arms/arm#1".
+    pub fn set_field(&mut self, path: &str, component: &str, field: &str, value: Value) -> &mut PrefabOverrides {
+        self.fields.push(FieldOverride {
+            path: String::from(path),
+            component: String::from(component),
+            field: String::from(field),
+            value,
+        });
+        self
+    }
+}
+
+pub(crate) fn spawn(scene: &mut Scene, prefab: &Prefab, overrides: &PrefabOverrides) -> Result<EntityId, SceneError> {
+    let root = scene.create_entity_with_name(prefab.name.clone());
+    match insert_tree(scene, prefab, root).and_then(|_| apply_overrides(scene, root, overrides)) {
+        Ok(()) => Ok(root),
+        Err(error) => {
+            scene.destroy_entity(root);
+            Err(error)
+        }
+    }
+}
+
+fn insert_tree(scene: &mut Scene, prefab: &Prefab, id: EntityId) -> Result<(), SceneError> {
+    for component in prefab.components.iter() {
+        component.insert(scene, id)?;
+    }
+    for child in prefab.children.iter() {
+        let child_id = scene.create_entity_with_name(child.name.clone());
+        scene.set_parent(child_id, id);
+        insert_tree(scene, child, child_id)?;
+    }
+    Ok(())
+}
+
+fn apply_overrides(scene: &mut Scene, root: EntityId, overrides: &PrefabOverrides) -> Result<(), SceneError> {
+    for field in overrides.fields.iter() {
+        let id = find_descendant(scene, root, &field.path)?;
+        serialization::override_field(scene, id, &field.component, &field.field, field.value.clone())?;
+    }
+    Ok(())
+}
+
+// A name shared by several children needs the index of the child among them, as in "arm#1"
+fn find_descendant(scene: &Scene, root: EntityId, path: &str) -> Result<EntityId, SceneError> {
+    let mut id = root;
+    for step in path.split('/').filter(|x| !x.is_empty()) {
+        let (name, index) = match step.rsplit_once('#').map(|(name, index)| (name, index.parse::<usize>())) {
+            Some((name, Ok(index))) => (name, Some(index)),
+            _ => (step, None),
+        };
+        let mut matches = scene
+            .get_children(id)
+            .iter()
+            .filter(|x| scene.get_entity_by_id(**x).unwrap().get_name() == name);
+        let found = match index {
+            Some(index) => matches.nth(index),
+            None => match (matches.next(), matches.next()) {
+                (Some(_), Some(_)) => {
+                    let reason = format!("more than one entity at path \"{}\"", path);
+                    return Err(SceneError::InvalidOverride(reason));
+                }
+                (first, _) => first,
+            },
+        };
+        id = match found {
+            Some(found) => *found,
+            None => return Err(SceneError::InvalidOverride(format!("no entity at path \"{}\"", path))),
+        };
+    }
+    Ok(id)
+}
@@ -0,0 +1,514 @@
+use crate::archetype::Archetype;
+use crate::entity::Component;
+use crate::entity::EntityId;
+use crate::storage;
+use crate::storage::ColumnState;
+use crate::storage::SparseSets;
+use crate::storage::StorageType;
+use std::any::type_name;
+use std::any::TypeId;
+use std::marker::PhantomData;
+
+#[derive(Clone, Default, Debug)]
+pub struct Access {
+    reads: Vec<(TypeId, &'static str)>,
+    writes: Vec<(TypeId, &'static str)>,
+}
+
+impl Access {
+    pub fn new() -> Access {
+        Access::default()
+    }
+
+    // Panics if T is already written by the same access set
+    pub fn add_read<T: 'static>(&mut self) {
+        let type_id = TypeId::of::<T>();
+        if self.writes.iter().any(|(x, _)| *x == type_id) {
+            panic!("{} is accessed both mutably and immutably", type_name::<T>());
+        }
+        if !self.reads.iter().any(|(x, _)| *x == type_id) {
+            self.reads.push((type_id, type_name::<T>()));
+        }
+    }
+
+    // Panics if T is already read or written by the same access set
+    pub fn add_write<T: 'static>(&mut self) {
+        let type_id = TypeId::of::<T>();
+        if self.writes.iter().any(|(x, _)| *x == type_id) {
+            panic!("{} is accessed mutably more than once", type_name::<T>());
+        }
+        if self.reads.iter().any(|(x, _)| *x == type_id) {
+            panic!("{} is accessed both mutably and immutably", type_name::<T>());
+        }
+        self.writes.push((type_id, type_name::<T>()));
+    }
+
+    pub fn reads(&self, type_id: TypeId) -> bool {
+        self.reads.iter().any(|(x, _)| *x == type_id)
+    }
+
+    pub fn writes(&self, type_id: TypeId) -> bool {
+        self.writes.iter().any(|(x, _)| *x == type_id)
+    }
+
+    // Returns the names of the types that prevent running both access sets at the same time
+    pub fn get_conflicts(&self, other: &Access) -> Vec<&'static str> {
+        let mut conflicts: Vec<&'static str> = vec![];
+        for (type_id, name) in self.writes.iter() {
+            if other.reads(*type_id) || other.writes(*type_id) {
+                conflicts.push(name);
+            }
+        }
+        for (type_id, name) in self.reads.iter() {
+            if other.writes(*type_id) {
+                conflicts.push(name);
+            }
+        }
+        conflicts
+    }
+
+    // Returns the name of the first type that is accessed here but not allowed by the declared access
+    pub fn find_undeclared(&self, declared: &Access) -> Option<&'static str> {
+        let read = self.reads.iter().find(|(x, _)| !declared.reads(*x) && !declared.writes(*x));
+        let write = self.writes.iter().find(|(x, _)| !declared.writes(*x));
+        read.or(write).map(|(_, name)| *name)
+    }
+}
+
+// The change ticks a query compares against and stamps its writes with
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Ticks {
+    last_run: u64,
+    this_run: u64,
+}
+
+impl Ticks {
+    pub(crate) fn new(last_run: u64, this_run: u64) -> Ticks {
+        Ticks { last_run, this_run }
+    }
+
+    pub fn get_last_run(&self) -> u64 {
+        self.last_run
+    }
+
+    pub fn get_this_run(&self) -> u64 {
+        self.this_run
+    }
+}
+
+/// # Safety
+///
+/// `add_access` must report every component that `fetch` hands out and
+/// `fetch` must only be called for rows of the archetype the state came from.
+pub unsafe trait Fetch {
+    type Item<'a>;
+    type State;
+
+    fn add_access(access: &mut Access);
+    fn matches(archetype: &Archetype) -> bool;
+
+    /// # Safety
+    ///
+    /// The archetype must match and the caller must hold the access reported by `add_access`.
+    unsafe fn get_state(archetype: &Archetype, sparse_sets: &SparseSets, ticks: Ticks) -> Self::State;
+
+    // Sparse set components are not part of the archetype, so a matching archetype can still have rows without them
+    /// # Safety
+    ///
+    /// The row must be in bounds of the archetype the state came from.
+    unsafe fn matches_row(_state: &Self::State, _row: usize) -> bool {
+        true
+    }
+
+    /// # Safety
+    ///
+    /// The row must be in bounds and match, and the returned item must not outlive the archetype.
+    unsafe fn fetch<'a>(state: &Self::State, row: usize) -> Self::Item<'a>;
+}
+
+unsafe impl Fetch for EntityId {
+    type Item<'a> = EntityId;
+    type State = *const EntityId;
+
+    fn add_access(_access: &mut Access) {}
+
+    fn matches(_archetype: &Archetype) -> bool {
+        true
+    }
+
+    unsafe fn get_state(archetype: &Archetype, _sparse_sets: &SparseSets, _ticks: Ticks) -> Self::State {
+        archetype.get_entities().as_ptr()
+    }
+
+    unsafe fn fetch<'a>(state: &Self::State, row: usize) -> Self::Item<'a> {
+        *state.add(row)
+    }
+}
+
+unsafe impl<T: Component + 'static> Fetch for &T {
+    type Item<'a> = &'a T;
+    type State = ColumnState<T>;
+
+    fn add_access(access: &mut Access) {
+        access.add_read::<T>();
+    }
+
+    fn matches(archetype: &Archetype) -> bool {
+        storage::may_have::<T>(archetype)
+    }
+
+    unsafe fn get_state(archetype: &Archetype, sparse_sets: &SparseSets, _ticks: Ticks) -> Self::State {
+        ColumnState::new(archetype, sparse_sets)
+    }
+
+    unsafe fn matches_row(state: &Self::State, row: usize) -> bool {
+        state.has(row)
+    }
+
+    unsafe fn fetch<'a>(state: &Self::State, row: usize) -> Self::Item<'a> {
+        &*state.get_unchecked(row).0
+    }
+}
+
+// Fetching a component mutably marks it as changed
+unsafe impl<T: Component + 'static> Fetch for &mut T {
+    type Item<'a> = &'a mut T;
+    type State = (ColumnState<T>, u64);
+
+    fn add_access(access: &mut Access) {
+        access.add_write::<T>();
+    }
+
+    fn matches(archetype: &Archetype) -> bool {
+        storage::may_have::<T>(archetype)
+    }
+
+    unsafe fn get_state(archetype: &Archetype, sparse_sets: &SparseSets, ticks: Ticks) -> Self::State {
+        (ColumnState::new(archetype, sparse_sets), ticks.this_run)
+    }
+
+    unsafe fn matches_row(state: &Self::State, row: usize) -> bool {
+        state.0.has(row)
+    }
+
+    unsafe fn fetch<'a>(state: &Self::State, row: usize) -> Self::Item<'a> {
+        let (value, component_ticks) = state.0.get_unchecked(row);
+        (*component_ticks).set_changed(state.1);
+        &mut *value
+    }
+}
+
+unsafe impl<T: Component + 'static> Fetch for Option<&T> {
+    type Item<'a> = Option<&'a T>;
+    type State = ColumnState<T>;
+
+    fn add_access(access: &mut Access) {
+        access.add_read::<T>();
+    }
+
+    fn matches(_archetype: &Archetype) -> bool {
+        true
+    }
+
+    unsafe fn get_state(archetype: &Archetype, sparse_sets: &SparseSets, _ticks: Ticks) -> Self::State {
+        ColumnState::new(archetype, sparse_sets)
+    }
+
+    unsafe fn fetch<'a>(state: &Self::State, row: usize) -> Self::Item<'a> {
+        state.get(row).map(|(value, _)| &*value)
+    }
+}
+
+macro_rules! impl_fetch_tuple {
+    ($($name:ident),*) => {
+        #[allow(non_snake_case)]
+        unsafe impl<$($name: Fetch),*> Fetch for ($($name,)*) {
+            type Item<'a> = ($($name::Item<'a>,)*);
+            type State = ($($name::State,)*);
+
+            fn add_access(access: &mut Access) {
+                $($name::add_access(access);)*
+            }
+
+            fn matches(archetype: &Archetype) -> bool {
+                true $(&& $name::matches(archetype))*
+            }
+
+            unsafe fn get_state(archetype: &Archetype, sparse_sets: &SparseSets, ticks: Ticks) -> Self::State {
+                ($($name::get_state(archetype, sparse_sets, ticks),)*)
+            }
+
+            unsafe fn matches_row(state: &Self::State, row: usize) -> bool {
+                let ($($name,)*) = state;
+                true $(&& $name::matches_row($name, row))*
+            }
+
+            unsafe fn fetch<'a>(state: &Self::State, row: usize) -> Self::Item<'a> {
+                let ($($name,)*) = state;
+                ($($name::fetch($name, row),)*)
+            }
+        }
+    };
+}
+
+impl_fetch_tuple!(A);
+impl_fetch_tuple!(A, B);
+impl_fetch_tuple!(A, B, C);
+impl_fetch_tuple!(A, B, C, D);
+impl_fetch_tuple!(A, B, C, D, E);
+impl_fetch_tuple!(A, B, C, D, E, F);
+
+// Filters first by archetype and then row by row for the filters that look at
+// change ticks or at sparse set components
+pub trait QueryFilter {
+    type State;
+
+    fn add_access(access: &mut Access);
+    fn matches(archetype: &Archetype) -> bool;
+
+    /// # Safety
+    ///
+    /// The archetype must match and the caller must hold the access reported by `add_access`.
+    unsafe fn get_state(archetype: &Archetype, sparse_sets: &SparseSets, ticks: Ticks) -> Self::State;
+
+    /// # Safety
+    ///
+    /// The row must be in bounds of the archetype the state came from.
+    unsafe fn matches_row(state: &Self::State, row: usize) -> bool;
+}
+
+pub struct With<T>(PhantomData<T>);
+
+pub struct Without<T>(PhantomData<T>);
+
+// Matches components added since the last run of the querying system
+pub struct Added<T>(PhantomData<T>);
+
+// Matches components added or mutably accessed since the last run of the querying system
+pub struct Changed<T>(PhantomData<T>);
+
+impl QueryFilter for () {
+    type State = ();
+
+    fn add_access(_access: &mut Access) {}
+
+    fn matches(_archetype: &Archetype) -> bool {
+        true
+    }
+
+    unsafe fn get_state(_archetype: &Archetype, _sparse_sets: &SparseSets, _ticks: Ticks) -> Self::State {}
+
+    unsafe fn matches_row(_state: &Self::State, _row: usize) -> bool {
+        true
+    }
+}
+
+impl<T: Component + 'static> QueryFilter for With<T> {
+    type State = ColumnState<T>;
+
+    fn add_access(access: &mut Access) {
+        add_membership_access::<T>(access);
+    }
+
+    fn matches(archetype: &Archetype) -> bool {
+        storage::may_have::<T>(archetype)
+    }
+
+    unsafe fn get_state(archetype: &Archetype, sparse_sets: &SparseSets, _ticks: Ticks) -> Self::State {
+        ColumnState::new(archetype, sparse_sets)
+    }
+
+    unsafe fn matches_row(state: &Self::State, row: usize) -> bool {
+        state.has(row)
+    }
+}
+
+impl<T: Component + 'static> QueryFilter for Without<T> {
+    type State = ColumnState<T>;
+
+    fn add_access(access: &mut Access) {
+        add_membership_access::<T>(access);
+    }
+
+    fn matches(archetype: &Archetype) -> bool {
+        T::STORAGE == StorageType::SparseSet || !archetype.has_component(TypeId::of::<T>())
+    }
+
+    unsafe fn get_state(archetype: &Archetype, sparse_sets: &SparseSets, _ticks: Ticks) -> Self::State {
+        ColumnState::new(archetype, sparse_sets)
+    }
+
+    unsafe fn matches_row(state: &Self::State, row: usize) -> bool {
+        T::STORAGE == StorageType::Table || !state.has(row)
+    }
+}
+
+// The ticks of T are read, which a query that also writes T already covers
+fn add_ticks_access<T: 'static>(access: &mut Access) {
+    if !access.writes(TypeId::of::<T>()) {
+        access.add_read::<T>();
+    }
+}
+
+// Rows are matched by looking up the entity in the sparse set of T, table components are matched by archetype
+fn add_membership_access<T: Component + 'static>(access: &mut Access) {
+    if T::STORAGE == StorageType::SparseSet {
+        add_ticks_access::<T>(access);
+    }
+}
+
+impl<T: Component + 'static> QueryFilter for Added<T> {
+    type State = (ColumnState<T>, u64);
+
+    fn add_access(access: &mut Access) {
+        add_ticks_access::<T>(access);
+    }
+
+    fn matches(archetype: &Archetype) -> bool {
+        storage::may_have::<T>(archetype)
+    }
+
+    unsafe fn get_state(archetype: &Archetype, sparse_sets: &SparseSets, ticks: Ticks) -> Self::State {
+        (ColumnState::new(archetype, sparse_sets), ticks.last_run)
+    }
+
+    unsafe fn matches_row(state: &Self::State, row: usize) -> bool {
+        state.0.get(row).is_some_and(|(_, ticks)| (*ticks).is_added(state.1))
+    }
+}
+
+impl<T: Component + 'static> QueryFilter for Changed<T> {
+    type State = (ColumnState<T>, u64);
+
+    fn add_access(access: &mut Access) {
+        add_ticks_access::<T>(access);
+    }
+
+    fn matches(archetype: &Archetype) -> bool {
+        storage::may_have::<T>(archetype)
+    }
+
+    unsafe fn get_state(archetype: &Archetype, sparse_sets: &SparseSets, ticks: Ticks) -> Self::State {
+        (ColumnState::new(archetype, sparse_sets), ticks.last_run)
+    }
+
+    unsafe fn matches_row(state: &Self::State, row: usize) -> bool {
+        state.0.get(row).is_some_and(|(_, ticks)| (*ticks).is_changed(state.1))
+    }
+}
+
+macro_rules! impl_filter_tuple {
+    ($($name:ident),*) => {
+        #[allow(non_snake_case)]
+        impl<$($name: QueryFilter),*> QueryFilter for ($($name,)*) {
+            type State = ($($name::State,)*);
+
+            fn add_access(access: &mut Access) {
+                $($name::add_access(access);)*
+            }
+
+            fn matches(archetype: &Archetype) -> bool {
+                true $(&& $name::matches(archetype))*
+            }
+
+            unsafe fn get_state(archetype: &Archetype, sparse_sets: &SparseSets, ticks: Ticks) -> Self::State {
+                ($($name::get_state(archetype, sparse_sets, ticks),)*)
+            }
+
+            unsafe fn matches_row(state: &Self::State, row: usize) -> bool {
+                let ($($name,)*) = state;
+                true $(&& $name::matches_row($name, row))*
+            }
+        }
+    };
+}
+
+impl_filter_tuple!(A);
+impl_filter_tuple!(A, B);
+impl_filter_tuple!(A, B, C);
+impl_filter_tuple!(A, B, C, D);
+
+// Entities that lost a component of type T since the last run of the querying system.
+// Removals are kept for two scene updates.
+pub struct RemovedComponents<'a, T> {
+    removed: std::slice::Iter<'a, (EntityId, u64)>,
+    last_run: u64,
+    component_type: PhantomData<fn() -> T>,
+}
+
+impl<'a, T> RemovedComponents<'a, T> {
+    pub(crate) fn new(removed: &'a [(EntityId, u64)], last_run: u64) -> RemovedComponents<'a, T> {
+        RemovedComponents {
+            removed: removed.iter(),
+            last_run,
+            component_type: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> Iterator for RemovedComponents<'a, T> {
+    type Item = EntityId;
+
+    fn next(&mut self) -> Option<EntityId> {
+        let last_run = self.last_run;
+        self.removed.find(|(_, tick)| *tick > last_run).map(|(id, _)| *id)
+    }
+}
+
+pub struct QueryIter<'s, Q: Fetch, F: QueryFilter = ()> {
+    archetypes: std::slice::Iter<'s, Archetype>,
+    sparse_sets: &'s SparseSets,
+    ticks: Ticks,
+    state: Option<(Q::State, F::State)>,
+    row: usize,
+    len: usize,
+}
+
+impl<'s, Q: Fetch, F: QueryFilter> QueryIter<'s, Q, F> {
+    // The caller must have exclusive access to the columns that Q writes
+    // and shared access to the columns that Q reads for the lifetime 's.
+    pub(crate) unsafe fn new(archetypes: &'s [Archetype], sparse_sets: &'s SparseSets, ticks: Ticks) -> QueryIter<'s, Q, F> {
+        let mut access = Access::new();
+        Q::add_access(&mut access);
+        F::add_access(&mut access);
+        QueryIter {
+            archetypes: archetypes.iter(),
+            sparse_sets,
+            ticks,
+            state: None,
+            row: 0,
+            len: 0,
+        }
+    }
+}
+
+impl<'s, Q: Fetch, F: QueryFilter> Iterator for QueryIter<'s, Q, F> {
+    type Item = Q::Item<'s>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((state, filter_state)) = &self.state {
+                while self.row < self.len {
+                    let row = self.row;
+                    self.row += 1;
+                    if unsafe { Q::matches_row(state, row) && F::matches_row(filter_state, row) } {
+                        return Some(unsafe { Q::fetch(state, row) });
+                    }
+                }
+            }
+            let archetype = self.archetypes.next()?;
+            self.state = None;
+            if !archetype.is_empty() && Q::matches(archetype) && F::matches(archetype) {
+                let state = unsafe {
+                    (
+                        Q::get_state(archetype, self.sparse_sets, self.ticks),
+                        F::get_state(archetype, self.sparse_sets, self.ticks),
+                    )
+                };
+                self.state = Some(state);
+                self.row = 0;
+                self.len = archetype.len();
+            }
+        }
+    }
+}
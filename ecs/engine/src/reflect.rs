@@ -0,0 +1,223 @@
+use crate::entity::EntityId;
+use crate::scene::Scene;
+use std::any::TypeId;
+use std::fmt;
+
+pub use engine_derive::Reflect;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FieldInfo {
+    name: &'static str,
+    type_name: &'static str,
+}
+
+impl FieldInfo {
+    pub fn new(name: &'static str, type_name: &'static str) -> FieldInfo {
+        FieldInfo { name, type_name }
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn get_type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+// Runtime access to the fields of a value, usually implemented with #[derive(Reflect)].
+// Values without fields are read and written as text.
+pub trait Reflect {
+    fn get_type_name(&self) -> &'static str;
+
+    fn get_fields(&self) -> Vec<FieldInfo> {
+        vec![]
+    }
+
+    fn get_field(&self, _name: &str) -> Option<&dyn Reflect> {
+        None
+    }
+
+    fn get_field_mut(&mut self, _name: &str) -> Option<&mut dyn Reflect> {
+        None
+    }
+
+    fn get_text(&self) -> String {
+        let fields: Vec<String> = self
+            .get_fields()
+            .iter()
+            .map(|x| format!("{}: {}", x.get_name(), self.get_field(x.get_name()).unwrap().get_text()))
+            .collect();
+        if fields.is_empty() {
+            String::from(self.get_type_name())
+        } else {
+            format!("{} {{ {} }}", self.get_type_name(), fields.join(", "))
+        }
+    }
+
+    // Returns false if the text is not a valid value
+    fn set_text(&mut self, _text: &str) -> bool {
+        false
+    }
+}
+
+// The path names nested fields separated by '.', the empty path is the value itself
+pub fn get_path<'a>(value: &'a dyn Reflect, path: &str) -> Option<&'a dyn Reflect> {
+    let mut value = value;
+    for name in path.split('.').filter(|x| !x.is_empty()) {
+        value = value.get_field(name)?;
+    }
+    Some(value)
+}
+
+pub fn get_path_mut<'a>(value: &'a mut dyn Reflect, path: &str) -> Option<&'a mut dyn Reflect> {
+    let mut value = value;
+    for name in path.split('.').filter(|x| !x.is_empty()) {
+        value = value.get_field_mut(name)?;
+    }
+    Some(value)
+}
+
+macro_rules! reflect_value {
+    ($($value:ty),*) => {
+        $(
+            impl Reflect for $value {
+                fn get_type_name(&self) -> &'static str {
+                    stringify!($value)
+                }
+
+                fn get_text(&self) -> String {
+                    format!("{:?}", self)
+                }
+
+                fn set_text(&mut self, text: &str) -> bool {
+                    match text.trim().parse::<$value>() {
+                        Ok(value) => {
+                            *self = value;
+                            true
+                        }
+                        Err(_) => false,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+reflect_value!(bool, char, u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64);
+
+// Read as a quoted string and written from the raw text
+impl Reflect for String {
+    fn get_type_name(&self) -> &'static str {
+        "String"
+    }
+
+    fn get_text(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    fn set_text(&mut self, text: &str) -> bool {
+        *self = String::from(text);
+        true
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ReflectError {
+    MissingEntity(EntityId),
+    MissingComponent(EntityId, String),
+    MissingField(String),
+    InvalidValue(String, String),
+}
+
+impl fmt::Display for ReflectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReflectError::MissingEntity(id) => write!(f, "entity {:?} does not exist", id),
+            ReflectError::MissingComponent(id, name) => {
+                write!(f, "entity {:?} has no reflected component {}", id, name)
+            }
+            ReflectError::MissingField(path) => write!(f, "no field at {}", path),
+            ReflectError::InvalidValue(path, text) => write!(f, "{} is not a valid value for {}", text, path),
+        }
+    }
+}
+
+impl std::error::Error for ReflectError {}
+
+// Paths start with the type name of the component, as in "CountComponent.count"
+fn split_path(path: &str) -> (&str, &str) {
+    path.split_once('.').unwrap_or((path, ""))
+}
+
+pub(crate) fn get_field_text(scene: &Scene, id: EntityId, path: &str) -> Result<String, ReflectError> {
+    if !scene.contains_entity(id) {
+        return Err(ReflectError::MissingEntity(id));
+    }
+    let (component_name, field_path) = split_path(path);
+    let component = scene
+        .get_reflected_components(id)
+        .into_iter()
+        .find(|x| x.get_type_name() == component_name)
+        .ok_or_else(|| ReflectError::MissingComponent(id, String::from(component_name)))?;
+    match get_path(component, field_path) {
+        Some(field) => Ok(field.get_text()),
+        None => Err(ReflectError::MissingField(String::from(path))),
+    }
+}
+
+// Returns the type of the reflected component the path starts with
+pub(crate) fn get_component_type(scene: &Scene, id: EntityId, path: &str) -> Option<TypeId> {
+    let (component_name, _) = split_path(path);
+    let registry = scene.get_component_registry();
+    scene
+        .get_component_types(id)
+        .into_iter()
+        .map(|(type_id, _)| type_id)
+        .find(|type_id| match registry.get_reflect(*type_id) {
+            Some(get) => get(scene, id).unwrap().get_type_name() == component_name,
+            None => false,
+        })
+}
+
+pub(crate) fn set_field_text(scene: &mut Scene, id: EntityId, path: &str, text: &str) -> Result<(), ReflectError> {
+    if !scene.contains_entity(id) {
+        return Err(ReflectError::MissingEntity(id));
+    }
+    let (component_name, field_path) = split_path(path);
+    // Only the written component is borrowed mutably so that no other component is marked changed
+    let get_mut = get_component_type(scene, id, path)
+        .and_then(|type_id| scene.get_component_registry().get_reflect_mut(type_id))
+        .ok_or_else(|| ReflectError::MissingComponent(id, String::from(component_name)))?;
+    let field = match get_path_mut(get_mut(scene, id).unwrap(), field_path) {
+        Some(field) => field,
+        None => return Err(ReflectError::MissingField(String::from(path))),
+    };
+    if !field.set_text(text) {
+        return Err(ReflectError::InvalidValue(String::from(path), String::from(text)));
+    }
+    Ok(())
+}
+
+// One line per entity followed by its components, reflected ones with their field values
+pub(crate) fn dump(scene: &Scene) -> String {
+    let registry = scene.get_component_registry();
+    let mut text = String::new();
+    for entity in scene.get_entities() {
+        let id = entity.get_id();
+        text += &format!("{} [{}.{}]\n", entity.get_name(), id.get_index(), id.get_generation());
+        let mut components: Vec<String> = scene
+            .get_component_types(id)
+            .into_iter()
+            .map(|(type_id, type_name)| match registry.get_reflect(type_id) {
+                Some(get) => get(scene, id).unwrap().get_text(),
+                None => String::from(type_name),
+            })
+            .collect();
+        components.sort();
+        for component in components {
+            text += &format!("    {}\n", component);
+        }
+    }
+    text
+}
@@ -0,0 +1,134 @@
+use crate::entity::EntityId;
+use crate::entity::EntityMap;
+use std::any::TypeId;
+use std::collections::HashMap;
+
+// A kind of directed link between two entities, e.g. struct Targets; impl Relation for Targets {}.
+// A source can have any number of targets of the same kind.
+pub trait Relation: Send + Sync + 'static {}
+
+// The pairs of one relation kind, indexed from both sides
+#[derive(Clone, Default)]
+struct RelationPairs {
+    targets: HashMap<EntityId, Vec<EntityId>>,
+    sources: HashMap<EntityId, Vec<EntityId>>,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct Relations {
+    kinds: HashMap<TypeId, RelationPairs>,
+}
+
+impl Relations {
+    // Returns false if the pair already exists
+    pub(crate) fn add(&mut self, kind: TypeId, source: EntityId, target: EntityId) -> bool {
+        let pairs = self.kinds.entry(kind).or_default();
+        let targets = pairs.targets.entry(source).or_default();
+        if targets.contains(&target) {
+            return false;
+        }
+        targets.push(target);
+        pairs.sources.entry(target).or_default().push(source);
+        true
+    }
+
+    pub(crate) fn remove(&mut self, kind: TypeId, source: EntityId, target: EntityId) -> bool {
+        let pairs = match self.kinds.get_mut(&kind) {
+            Some(pairs) => pairs,
+            None => return false,
+        };
+        if !unlink(&mut pairs.targets, source, target) {
+            return false;
+        }
+        unlink(&mut pairs.sources, target, source);
+        true
+    }
+
+    pub(crate) fn contains(&self, kind: TypeId, source: EntityId, target: EntityId) -> bool {
+        self.get_targets(kind, source).contains(&target)
+    }
+
+    // Targets are in the order they were added
+    pub(crate) fn get_targets(&self, kind: TypeId, source: EntityId) -> &[EntityId] {
+        self.kinds
+            .get(&kind)
+            .and_then(|x| x.targets.get(&source))
+            .map_or(&[], |x| x.as_slice())
+    }
+
+    // Sources are in the order they were added
+    pub(crate) fn get_sources(&self, kind: TypeId, target: EntityId) -> &[EntityId] {
+        self.kinds
+            .get(&kind)
+            .and_then(|x| x.sources.get(&target))
+            .map_or(&[], |x| x.as_slice())
+    }
+
+    pub(crate) fn get_pairs(&self, kind: TypeId) -> impl Iterator<Item = (EntityId, EntityId)> + '_ {
+        self.kinds
+            .get(&kind)
+            .into_iter()
+            .flat_map(|x| x.targets.iter())
+            .flat_map(|(source, targets)| targets.iter().map(move |target| (*source, *target)))
+    }
+
+    // Every (kind, source, target) pair that has the entity on either side
+    pub(crate) fn get_entity_pairs(&self, id: EntityId) -> Vec<(TypeId, EntityId, EntityId)> {
+        let mut pairs = vec![];
+        for (kind, kind_pairs) in self.kinds.iter() {
+            for target in kind_pairs.targets.get(&id).into_iter().flatten() {
+                pairs.push((*kind, id, *target));
+            }
+            for source in kind_pairs.sources.get(&id).into_iter().flatten() {
+                if *source != id {
+                    pairs.push((*kind, *source, id));
+                }
+            }
+        }
+        pairs
+    }
+
+    // Copies the pairs between mapped entities to the other relations under their new ids
+    pub(crate) fn copy_mapped(&self, other: &mut Relations, map: &EntityMap) {
+        for (kind, pairs) in self.kinds.iter() {
+            for (source, targets) in pairs.targets.iter() {
+                let source = match map.get(*source) {
+                    Some(source) => source,
+                    None => continue,
+                };
+                for target in targets.iter().filter_map(|x| map.get(*x)) {
+                    other.add(*kind, source, target);
+                }
+            }
+        }
+    }
+
+    // Drops every pair of every kind that has the entity on either side
+    pub(crate) fn remove_entity(&mut self, id: EntityId) {
+        for pairs in self.kinds.values_mut() {
+            for target in pairs.targets.remove(&id).unwrap_or_default() {
+                unlink(&mut pairs.sources, target, id);
+            }
+            for source in pairs.sources.remove(&id).unwrap_or_default() {
+                unlink(&mut pairs.targets, source, id);
+            }
+        }
+    }
+}
+
+// Removes the entity from the list of the key and the list itself once it is empty
+fn unlink(links: &mut HashMap<EntityId, Vec<EntityId>>, key: EntityId, id: EntityId) -> bool {
+    let list = match links.get_mut(&key) {
+        Some(list) => list,
+        None => return false,
+    };
+    let index = match list.iter().position(|x| *x == id) {
+        Some(index) => index,
+        None => return false,
+    };
+    list.remove(index);
+    if list.is_empty() {
+        links.remove(&key);
+    }
+    true
+}
@@ -0,0 +1,196 @@
+use std::any::Any;
+use std::any::TypeId;
+use std::cell::UnsafeCell;
+use std::collections::HashMap;
+use std::time::Duration;
+use std::time::Instant;
+
+type ResourceCell = UnsafeCell<Box<dyn Any + Send + Sync>>;
+
+// Global values of the scene keyed by their type
+#[derive(Default)]
+pub struct Resources {
+    values: HashMap<TypeId, ResourceCell>,
+}
+
+// Resources are only written through a shared reference by systems whose declared
+// access the schedule has checked not to conflict with any concurrently running system.
+unsafe impl Sync for Resources {}
+
+impl Resources {
+    pub fn new() -> Resources {
+        Resources::default()
+    }
+
+    // Returns the previous value if a resource of the same type was already present
+    pub fn insert<R: Send + Sync + 'static>(&mut self, resource: R) -> Option<R> {
+        let previous = self.values.insert(TypeId::of::<R>(), UnsafeCell::new(Box::new(resource)))?;
+        Some(*previous.into_inner().downcast::<R>().unwrap())
+    }
+
+    pub fn remove<R: Send + Sync + 'static>(&mut self) -> Option<R> {
+        let cell = self.values.remove(&TypeId::of::<R>())?;
+        Some(*cell.into_inner().downcast::<R>().unwrap())
+    }
+
+    pub fn contains<R: Send + Sync + 'static>(&self) -> bool {
+        self.values.contains_key(&TypeId::of::<R>())
+    }
+
+    pub fn get<R: Send + Sync + 'static>(&self) -> Option<&R> {
+        let cell = self.values.get(&TypeId::of::<R>())?;
+        unsafe { &*cell.get() }.downcast_ref::<R>()
+    }
+
+    pub fn get_mut<R: Send + Sync + 'static>(&mut self) -> Option<&mut R> {
+        let cell = self.values.get_mut(&TypeId::of::<R>())?;
+        cell.get_mut().downcast_mut::<R>()
+    }
+
+    /// # Safety
+    ///
+    /// The caller must have exclusive access to the resource while the returned reference is used.
+    #[allow(clippy::mut_from_ref)]
+    pub(crate) unsafe fn get_unchecked_mut<R: Send + Sync + 'static>(&self) -> Option<&mut R> {
+        let cell = self.values.get(&TypeId::of::<R>())?;
+        (*cell.get()).downcast_mut::<R>()
+    }
+}
+
+// Frame timing, advanced by Scene::update before any stage runs
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Time {
+    delta: Duration,
+    elapsed: Duration,
+    frame_count: u64,
+    last_instant: Option<Instant>,
+}
+
+impl Time {
+    pub fn new() -> Time {
+        Time::default()
+    }
+
+    pub fn get_delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn get_delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    pub fn get_elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn get_frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    // The first frame has a delta of zero
+    pub(crate) fn advance(&mut self, now: Instant) {
+        self.delta = match self.last_instant {
+            Some(last) => now.saturating_duration_since(last),
+            None => Duration::ZERO,
+        };
+        self.last_instant = Some(now);
+        self.elapsed += self.delta;
+        self.frame_count += 1;
+    }
+}
+
+// Accumulates frame time so that simulation code can advance in steps of a fixed length.
+// Scene::update runs the FixedUpdate stage once for every whole step, at most max_steps times per frame.
+#[derive(Clone, Copy, Debug)]
+pub struct FixedTime {
+    step: Duration,
+    accumulator: Duration,
+    max_steps: u32,
+    frame_steps: u32,
+}
+
+impl Default for FixedTime {
+    fn default() -> FixedTime {
+        FixedTime::new(Duration::from_secs(1) / 60)
+    }
+}
+
+impl FixedTime {
+    pub fn new(step: Duration) -> FixedTime {
+        if step.is_zero() {
+            panic!("fixed time step must not be zero");
+        }
+        FixedTime {
+            step,
+            accumulator: Duration::ZERO,
+            max_steps: 8,
+            frame_steps: 0,
+        }
+    }
+
+    pub fn get_step(&self) -> Duration {
+        self.step
+    }
+
+    pub fn set_step(&mut self, step: Duration) {
+        if step.is_zero() {
+            panic!("fixed time step must not be zero");
+        }
+        self.step = step;
+    }
+
+    pub fn get_max_steps(&self) -> u32 {
+        self.max_steps
+    }
+
+    // Limits how many steps a slow frame catches up on. Whole steps beyond the limit are dropped.
+    pub fn set_max_steps(&mut self, max_steps: u32) {
+        self.max_steps = max_steps;
+    }
+
+    // The number of steps run by the last update
+    pub fn get_frame_steps(&self) -> u32 {
+        self.frame_steps
+    }
+
+    pub fn get_accumulator(&self) -> Duration {
+        self.accumulator
+    }
+
+    // Fraction of a step that is accumulated but not yet expended, the interpolation alpha for rendering
+    pub fn get_overstep(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.step.as_secs_f32()
+    }
+
+    pub fn accumulate(&mut self, delta: Duration) {
+        self.accumulator += delta;
+    }
+
+    // Consumes one step from the accumulator. Returns false if less than a step is accumulated.
+    pub fn expend(&mut self) -> bool {
+        if self.accumulator < self.step {
+            return false;
+        }
+        self.accumulator -= self.step;
+        true
+    }
+
+    pub(crate) fn begin_frame(&mut self) {
+        self.frame_steps = 0;
+    }
+
+    // Like expend, but drops the remaining whole steps once the frame ran max_steps of them
+    pub(crate) fn expend_frame_step(&mut self) -> bool {
+        if self.frame_steps == self.max_steps {
+            // The remainder is less than a step, so it fits the nanos of a Duration however long the stall was
+            let remainder = self.accumulator.as_nanos() % self.step.as_nanos();
+            self.accumulator = Duration::from_nanos(remainder as u64);
+            return false;
+        }
+        if !self.expend() {
+            return false;
+        }
+        self.frame_steps += 1;
+        true
+    }
+}
@@ -0,0 +1,125 @@
+use crate::resource::FixedTime;
+use crate::scene::Scene;
+use std::time::Duration;
+use std::time::Instant;
+
+// Source of the frame instants passed to Scene::update_at
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+// Only moves when advanced, so tests can step through frames without sleeping
+#[derive(Clone, Copy, Debug)]
+pub struct ManualClock {
+    now: Instant,
+}
+
+impl Default for ManualClock {
+    fn default() -> ManualClock {
+        ManualClock::new()
+    }
+}
+
+impl ManualClock {
+    pub fn new() -> ManualClock {
+        ManualClock { now: Instant::now() }
+    }
+
+    pub fn advance(&mut self, delta: Duration) {
+        self.now += delta;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.now
+    }
+}
+
+// Drives a scene with one variable rate update per frame. Every update runs the FixedUpdate
+// stage for the fixed steps the frame completed, see FixedTime.
+pub struct Runner<C: Clock = SystemClock> {
+    scene: Scene,
+    clock: C,
+}
+
+impl Runner<SystemClock> {
+    pub fn new(scene: Scene) -> Runner<SystemClock> {
+        Runner::with_clock(scene, SystemClock)
+    }
+}
+
+impl<C: Clock> Runner<C> {
+    pub fn with_clock(scene: Scene, clock: C) -> Runner<C> {
+        Runner { scene, clock }
+    }
+
+    pub fn get_scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    pub fn get_scene_mut(&mut self) -> &mut Scene {
+        &mut self.scene
+    }
+
+    pub fn into_scene(self) -> Scene {
+        self.scene
+    }
+
+    pub fn get_clock(&self) -> &C {
+        &self.clock
+    }
+
+    pub fn get_clock_mut(&mut self) -> &mut C {
+        &mut self.clock
+    }
+
+    // Panics if the rate is zero
+    pub fn set_tick_rate(&mut self, ticks_per_second: u32) {
+        if ticks_per_second == 0 {
+            panic!("tick rate must not be zero");
+        }
+        self.get_fixed_time_mut().set_step(Duration::from_secs(1) / ticks_per_second);
+    }
+
+    pub fn set_max_catch_up_steps(&mut self, max_steps: u32) {
+        self.get_fixed_time_mut().set_max_steps(max_steps);
+    }
+
+    // How far the simulation is into the next fixed step, for interpolating between the last two steps
+    pub fn get_alpha(&self) -> f32 {
+        self.scene.get_resource::<FixedTime>().map_or(0.0, |x| x.get_overstep())
+    }
+
+    // Returns the number of fixed steps the frame ran
+    pub fn run_frame(&mut self) -> u32 {
+        self.scene.update_at(self.clock.now());
+        self.scene.get_resource::<FixedTime>().map_or(0, |x| x.get_frame_steps())
+    }
+
+    // Runs frames until the condition holds after one of them
+    pub fn run_until<F: FnMut(&Scene) -> bool>(&mut self, mut done: F) {
+        loop {
+            self.run_frame();
+            if done(&self.scene) {
+                break;
+            }
+        }
+    }
+
+    fn get_fixed_time_mut(&mut self) -> &mut FixedTime {
+        if !self.scene.has_resource::<FixedTime>() {
+            self.scene.insert_resource(FixedTime::default());
+        }
+        self.scene.get_resource_mut::<FixedTime>().unwrap()
+    }
+}
@@ -1,96 +1,1322 @@
+use crate::archetype::Archetype;
+use crate::archetype::ComponentTicks;
+use crate::archetype::EntityLocation;
+use crate::command::Commands;
+use crate::entity::Component;
 use crate::entity::Entity;
-use std::cell::RefCell;
+use crate::entity::EntityId;
+use crate::entity::EntityMap;
+use crate::entity_ref::EntityMut;
+use crate::entity_ref::EntityRef;
+use crate::event;
+use crate::event::EventWriter;
+use crate::event::Events;
+use crate::hierarchy::Children;
+use crate::hierarchy::Parent;
+use crate::history;
+use crate::history::DestroyedEntities;
+use crate::history::Edit;
+use crate::history::History;
+use crate::observer::ObserverId;
+use crate::observer::Observers;
+use crate::observer::Trigger;
+use crate::observer::TriggerKind;
+use crate::prefab;
+use crate::prefab::Prefab;
+use crate::prefab::PrefabOverrides;
+use crate::query::Fetch;
+use crate::query::QueryFilter;
+use crate::query::QueryIter;
+use crate::query::RemovedComponents;
+use crate::query::Ticks;
+use crate::reflect;
+use crate::reflect::Reflect;
+use crate::reflect::ReflectError;
+use crate::relation::Relation;
+use crate::relation::Relations;
+use crate::resource::FixedTime;
+use crate::resource::Resources;
+use crate::resource::Time;
+use crate::schedule;
+use crate::schedule::RunCondition;
+use crate::schedule::Schedule;
+use crate::schedule::Stage;
+use crate::schedule::SystemConfig;
+use crate::serialization;
+use crate::serialization::ComponentRegistry;
+use crate::serialization::SceneError;
+use crate::serialization::SerializationReport;
+use crate::snapshot;
+use crate::snapshot::SceneSnapshot;
+use crate::state;
+use crate::state::StateTransitions;
+use crate::state::States;
+use crate::state::Transition;
+use crate::state::Transitions;
+use crate::storage::SparseSets;
+use crate::storage::StorageType;
+use crate::system::System;
+use crate::transform;
+use crate::transform::Transform;
+use std::any::Any;
+use std::any::TypeId;
+use std::collections::HashMap;
 use std::option::Option;
-use std::rc::Rc;
+use std::path::Path;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+struct EntitySlot {
+    generation: u32,
+    entity: Option<Entity>,
+    location: EntityLocation,
+}
+
+// The entities that share a name in the order they were given it. Removing one leaves a hole
+// that is skipped, and the holes are dropped once they outnumber the entities.
+#[derive(Clone, Default)]
+struct NamedEntities {
+    ids: Vec<Option<EntityId>>,
+    positions: HashMap<EntityId, usize>,
+    first: usize,
+}
+
+impl NamedEntities {
+    fn push(&mut self, id: EntityId) {
+        self.positions.insert(id, self.ids.len());
+        self.ids.push(Some(id));
+    }
+
+    // Returns false once no entity has the name
+    fn remove(&mut self, id: EntityId) -> bool {
+        if let Some(position) = self.positions.remove(&id) {
+            self.ids[position] = None;
+        }
+        while self.ids.get(self.first).is_some_and(|x| x.is_none()) {
+            self.first += 1;
+        }
+        if self.ids.len() > 2 * self.positions.len() {
+            self.ids.retain(|x| x.is_some());
+            self.first = 0;
+            for (position, id) in self.ids.iter().enumerate() {
+                self.positions.insert(id.unwrap(), position);
+            }
+        }
+        !self.positions.is_empty()
+    }
+
+    fn get_first(&self) -> Option<EntityId> {
+        self.ids.get(self.first).copied().flatten()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = EntityId> + '_ {
+        self.ids[self.first..].iter().filter_map(|x| *x)
+    }
+}
+
+// Decides which ids are valid and which ids are allocated next
+#[derive(Clone)]
+pub(crate) struct EntityIds {
+    generations: Vec<u32>,
+    free_indices: Vec<u32>,
+    entity_names: HashMap<String, NamedEntities>,
+    last_id: u64,
+}
+
+// Drops the old events of one event type
+type EventUpdater = (TypeId, fn(&mut Resources));
 
 pub struct Scene {
-    entities: Vec<Rc<RefCell<Entity>>>,
+    slots: Vec<EntitySlot>,
+    free_indices: Vec<u32>,
+    entity_count: usize,
+    entity_names: HashMap<String, NamedEntities>,
     last_id: u64,
+    archetypes: Vec<Archetype>,
+    archetype_ids: HashMap<Vec<TypeId>, usize>,
+    sparse_sets: SparseSets,
+    schedule: Schedule,
+    state_transitions: Vec<(TypeId, Box<dyn StateTransitions>)>,
+    component_update_condition: Option<RunCondition>,
+    resources: Resources,
+    event_updaters: Vec<EventUpdater>,
+    component_registry: ComponentRegistry,
+    change_tick: AtomicU64,
+    last_update_tick: u64,
+    removed_components: HashMap<TypeId, Vec<(EntityId, u64)>>,
+    observers: Observers,
+    relations: Relations,
+    history: History,
+}
+
+// Scenes are built on loader threads and read from render threads, so losing Send or Sync is a build error
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Scene>();
+    assert_send_sync::<SceneSnapshot>();
+    assert_send_sync::<Prefab>();
+    assert_send_sync::<Commands>();
+};
+
+impl Default for Scene {
+    fn default() -> Scene {
+        Scene::new()
+    }
 }
 
 impl Scene {
     pub fn new() -> Scene {
-        Scene {
-            entities: vec![],
+        let mut scene = Scene {
+            slots: vec![],
+            free_indices: vec![],
+            entity_count: 0,
+            entity_names: HashMap::new(),
             last_id: 0,
+            archetypes: vec![Archetype::empty()],
+            archetype_ids: vec![(vec![], 0)].into_iter().collect(),
+            sparse_sets: SparseSets::default(),
+            schedule: Schedule::new(),
+            state_transitions: vec![],
+            component_update_condition: None,
+            resources: Resources::new(),
+            event_updaters: vec![],
+            component_registry: ComponentRegistry::new(),
+            change_tick: AtomicU64::new(1),
+            last_update_tick: 0,
+            removed_components: HashMap::new(),
+            observers: Observers::default(),
+            relations: Relations::default(),
+            history: History::new(),
+        };
+        scene.insert_resource(Time::new());
+        scene.insert_resource(FixedTime::default());
+        scene
+    }
+
+    pub fn update(&mut self) {
+        self.update_at(Instant::now());
+    }
+
+    // Advances the time resources to the given instant, drops old events, applies requested
+    // state transitions and runs the stages in order.
+    // FixedUpdate runs once for every fixed step that the frame time completes.
+    // Component updates run at the start of the Update stage and
+    // transforms are propagated before the Render stage.
+    // Commands are applied after the component updates and at the end of every stage.
+    pub fn update_at(&mut self, now: Instant) {
+        // The history holds edits made between updates, not the changes an update makes
+        self.without_recording(|scene| scene.run_frame(now));
+    }
+
+    fn run_frame(&mut self, now: Instant) {
+        let previous_update_tick = self.last_update_tick;
+        for removed in self.removed_components.values_mut() {
+            removed.retain(|(_, tick)| *tick > previous_update_tick);
+        }
+        self.last_update_tick = self.increment_change_tick();
+        self.advance_time(now);
+        for (_, update) in self.event_updaters.iter() {
+            update(&mut self.resources);
+        }
+        self.apply_state_transitions();
+        let mut schedule = std::mem::take(&mut self.schedule);
+        schedule.begin_frame();
+        for stage in Stage::ALL.iter() {
+            if *stage == Stage::Update {
+                self.update_components();
+            }
+            if *stage == Stage::Render {
+                self.propagate_transforms();
+            }
+            if *stage == Stage::FixedUpdate {
+                self.run_fixed_steps(&mut schedule);
+            } else {
+                schedule.run_stage(*stage, self);
+            }
+        }
+        self.schedule = schedule;
+    }
+
+    fn run_fixed_steps(&mut self, schedule: &mut Schedule) {
+        while self.resources.get_mut::<FixedTime>().is_some_and(|x| x.expend_frame_step()) {
+            schedule.run_stage(Stage::FixedUpdate, self);
+        }
+    }
+
+    fn advance_time(&mut self, now: Instant) {
+        let delta = match self.resources.get_mut::<Time>() {
+            Some(time) => {
+                time.advance(now);
+                time.get_delta()
+            }
+            None => return,
+        };
+        if let Some(fixed_time) = self.resources.get_mut::<FixedTime>() {
+            fixed_time.begin_frame();
+            fixed_time.accumulate(delta);
         }
     }
 
-    pub fn update(&self) {
-        for entity in self.entities.iter() {
-            entity.borrow().update();
+    fn apply_state_transitions(&mut self) {
+        let mut transitions = std::mem::take(&mut self.state_transitions);
+        for (_, transition) in transitions.iter_mut() {
+            transition.apply(self);
         }
+        // States initialized by transition systems take effect on the next update
+        transitions.append(&mut self.state_transitions);
+        self.state_transitions = transitions;
     }
 
-    pub fn create_entity(&mut self) -> Rc<RefCell<Entity>> {
-        self.last_id = self.last_id + 1;
-        let name: String = String::from("Entity ") + &(self.last_id.to_string());
-        self.entities
-            .push(Rc::new(RefCell::new(Entity::new(self.last_id, name))));
-        self.entities.last().unwrap().clone()
+    fn update_components(&mut self) {
+        if self.component_update_condition.as_ref().is_some_and(|condition| !condition(self)) {
+            return;
+        }
+        let mut commands = Commands::new();
+        let tick = self.increment_change_tick();
+        for archetype in self.archetypes.iter_mut() {
+            archetype.update(&self.resources, &mut commands, tick);
+        }
+        self.sparse_sets.update(&self.resources, &mut commands, tick);
+        commands.apply(self);
+    }
+
+    // Changes made outside of systems are marked with the current tick
+    pub fn get_change_tick(&self) -> u64 {
+        self.change_tick.load(Ordering::Relaxed)
+    }
+
+    // Returns the tick for a system run and advances the current tick past it
+    pub(crate) fn increment_change_tick(&self) -> u64 {
+        self.change_tick.fetch_add(1, Ordering::Relaxed)
+    }
+
+    // Change detection outside of systems is relative to the start of the last update
+    pub fn get_last_update_tick(&self) -> u64 {
+        self.last_update_tick
+    }
+
+    fn get_scene_ticks(&self) -> Ticks {
+        Ticks::new(self.last_update_tick, self.get_change_tick())
+    }
+
+    fn record_removed(&mut self, id: EntityId, type_id: TypeId) {
+        let tick = self.get_change_tick();
+        self.removed_components.entry(type_id).or_default().push((id, tick));
+    }
+
+    // Returns the entities that lost a component of type T since the start of the last update
+    pub fn get_removed_components<T: Component + 'static>(&self) -> RemovedComponents<'_, T> {
+        self.get_removed_components_since::<T>(self.last_update_tick)
+    }
+
+    pub(crate) fn get_removed_components_since<T: Component + 'static>(&self, last_run: u64) -> RemovedComponents<'_, T> {
+        match self.removed_components.get(&TypeId::of::<T>()) {
+            Some(removed) => RemovedComponents::new(removed, last_run),
+            None => RemovedComponents::new(&[], last_run),
+        }
     }
 
-    pub fn create_entity_with_name(&mut self, name: String) -> Rc<RefCell<Entity>> {
-        self.last_id = self.last_id + 1;
-        self.entities
-            .push(Rc::new(RefCell::new(Entity::new(self.last_id, name))));
-        self.entities.last().unwrap().clone()
+    // Applies the commands right away, in the order they were pushed
+    pub fn apply_commands(&mut self, commands: &mut Commands) {
+        commands.apply(self);
     }
 
-    pub fn destroy_entity(&mut self, entity: &Rc<RefCell<Entity>>) -> bool {
-        let entity_id = entity.borrow().get_id();
-        let index = self
-            .entities
+    // Returns the previous value if a resource of the same type was already present
+    pub fn insert_resource<R: Send + Sync + 'static>(&mut self, resource: R) -> Option<R> {
+        self.resources.insert(resource)
+    }
+
+    pub fn remove_resource<R: Send + Sync + 'static>(&mut self) -> Option<R> {
+        self.resources.remove::<R>()
+    }
+
+    pub fn has_resource<R: Send + Sync + 'static>(&self) -> bool {
+        self.resources.contains::<R>()
+    }
+
+    pub fn get_resource<R: Send + Sync + 'static>(&self) -> Option<&R> {
+        self.resources.get::<R>()
+    }
+
+    pub fn get_resource_mut<R: Send + Sync + 'static>(&mut self) -> Option<&mut R> {
+        self.resources.get_mut::<R>()
+    }
+
+    pub fn get_resources(&self) -> &Resources {
+        &self.resources
+    }
+
+    // Registers the Events<E> resource and drops its events two updates after they were sent
+    pub fn add_event<E: Send + Sync + 'static>(&mut self) {
+        let type_id = TypeId::of::<E>();
+        if !self.event_updaters.iter().any(|(x, _)| *x == type_id) {
+            self.event_updaters.push((type_id, event::update_events::<E>));
+        }
+        if !self.has_resource::<Events<E>>() {
+            self.insert_resource(Events::<E>::new());
+        }
+    }
+
+    pub fn get_events<E: Send + Sync + 'static>(&self) -> Option<&Events<E>> {
+        self.get_resource::<Events<E>>()
+    }
+
+    pub fn get_event_writer<E: Send + Sync + 'static>(&mut self) -> Option<EventWriter<'_, E>> {
+        self.get_resource_mut::<Events<E>>().map(EventWriter::new)
+    }
+
+    pub fn add_system<S: System + 'static>(&mut self, stage: Stage, system: S) -> SystemConfig<'_> {
+        self.schedule.add_system(stage, system)
+    }
+
+    // Adds the State and NextState resources of S. Panics if S already has them.
+    pub fn init_state<S: States>(&mut self, initial: S) {
+        let type_id = TypeId::of::<S>();
+        if self.state_transitions.iter().any(|(x, _)| *x == type_id) {
+            panic!("state {} is already initialized", std::any::type_name::<S>());
+        }
+        let (state, next_state) = state::create_resources(initial);
+        self.insert_resource(state);
+        self.insert_resource(next_state);
+        self.state_transitions.push((type_id, Box::new(Transitions::<S>::new())));
+    }
+
+    // Adds a system to the OnEnter or OnExit schedule of a state. Panics if the state type is not initialized.
+    pub fn add_transition_system<T: Transition, S: System + 'static>(&mut self, transition: T, system: S) -> SystemConfig<'_> {
+        let type_id = TypeId::of::<T::State>();
+        let transitions = match self.state_transitions.iter_mut().find(|(x, _)| *x == type_id) {
+            Some((_, transitions)) => transitions.as_any_mut().downcast_mut::<Transitions<T::State>>().unwrap(),
+            None => panic!("state {} is not initialized", std::any::type_name::<T::State>()),
+        };
+        let (kind, state) = transition.into_parts();
+        transitions.get_schedule_mut(kind, state).add_system(Stage::Update, system)
+    }
+
+    // Component updates only run while the state resource of S holds the given state
+    pub fn run_component_updates_in_state<S: States>(&mut self, state: S) {
+        self.component_update_condition = Some(schedule::in_state(state));
+    }
+
+    pub fn get_schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    pub fn get_schedule_mut(&mut self) -> &mut Schedule {
+        &mut self.schedule
+    }
+
+    pub fn get_component_registry(&self) -> &ComponentRegistry {
+        &self.component_registry
+    }
+
+    pub fn get_component_registry_mut(&mut self) -> &mut ComponentRegistry {
+        &mut self.component_registry
+    }
+
+    pub fn get_history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn get_history_mut(&mut self) -> &mut History {
+        &mut self.history
+    }
+
+    // Reverts the last transaction of the history, closing the open one first.
+    // Returns false if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let transaction = match self.history.take_undo() {
+            Some(transaction) => transaction,
+            None => return false,
+        };
+        self.without_recording(|scene| transaction.undo(scene));
+        self.history.push_undone(transaction);
+        true
+    }
+
+    // Applies the last undone transaction again. Returns false if there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let transaction = match self.history.take_redo() {
+            Some(transaction) => transaction,
+            None => return false,
+        };
+        self.without_recording(|scene| transaction.redo(scene));
+        self.history.push_redone(transaction);
+        true
+    }
+
+    // Runs changes that are part of an edit being recorded, or that the history does not track
+    fn without_recording<R, F: FnOnce(&mut Scene) -> R>(&mut self, change: F) -> R {
+        self.history.suspend();
+        let result = change(self);
+        self.history.resume();
+        result
+    }
+
+    // Writes all entities with their names, ids, parents and registered components.
+    // Components that are not registered are left out and listed in the report.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<SerializationReport, SceneError> {
+        serialization::save(self, path.as_ref())
+    }
+
+    // Replaces all entities of the scene with the saved ones, keeping their ids.
+    // Components whose names are not registered are left out and listed in the report.
+    // The history is cleared as its edits refer to the replaced entities.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<SerializationReport, SceneError> {
+        let report = self.without_recording(|scene| serialization::load(scene, path.as_ref()))?;
+        self.history.clear();
+        Ok(report)
+    }
+
+    // Copies the entities with their ids and the components registered in the component registry
+    pub fn snapshot(&self) -> SceneSnapshot {
+        snapshot::take(self)
+    }
+
+    // Returns the entities to the state of the snapshot. Ids that were valid when the snapshot was taken
+    // are valid again, ids created after it are not. Restored components count as added.
+    // Restoring is not recorded, the history still applies if it was not changed since the snapshot.
+    pub fn restore(&mut self, snapshot: &SceneSnapshot) {
+        self.without_recording(|scene| snapshot::restore(scene, snapshot));
+    }
+
+    // Reads a prefab saved in the scene format, see serialization::load_prefab.
+    // Unlike scene loading, components missing from the registry are an error.
+    pub fn load_prefab<P: AsRef<Path>>(&self, path: P) -> Result<Prefab, SceneError> {
+        serialization::load_prefab(&self.component_registry, path.as_ref())
+    }
+
+    // Creates a copy of the prefab's entity tree and returns its root
+    pub fn spawn_prefab(&mut self, prefab: &Prefab) -> Result<EntityId, SceneError> {
+        self.spawn_prefab_with_overrides(prefab, &PrefabOverrides::new())
+    }
+
+    // Nothing is left in the scene if a component or an override fails to apply.
+    // The spawned tree is recorded as one edit once its overrides are applied.
+    pub fn spawn_prefab_with_overrides(&mut self, prefab: &Prefab, overrides: &PrefabOverrides) -> Result<EntityId, SceneError> {
+        let root = self.without_recording(|scene| prefab::spawn(scene, prefab, overrides))?;
+        if self.history.is_recording() {
+            let edit = DestroyedEntities::take(self, root).map(Edit::SpawnEntities);
+            self.history.push("spawn prefab", edit);
+        }
+        Ok(root)
+    }
+
+    // Runs the callback right after every matching change, with the entity that changed.
+    // Changes made through commands trigger observers when the commands are applied.
+    // An observer is not run again for the changes it makes itself.
+    pub fn observe<T: Trigger, F: FnMut(EntityId, &mut Scene) + Send + Sync + 'static>(&mut self, callback: F) -> ObserverId {
+        self.observers.add(T::get_kind(), Box::new(callback))
+    }
+
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
+        self.observers.remove(id)
+    }
+
+    // Links the source to the target. Returns false if either entity does not exist or the pair already does.
+    // Pairs are removed together with either of their entities.
+    pub fn add_relation<R: Relation>(&mut self, source: EntityId, target: EntityId) -> bool {
+        if !self.contains_entity(source) || !self.contains_entity(target) {
+            return false;
+        }
+        let added = self.relations.add(TypeId::of::<R>(), source, target);
+        if added && self.history.is_recording() {
+            let edit = Edit::SetRelation { kind: TypeId::of::<R>(), source, target, added: true };
+            self.history.push("add relation", Some(edit));
+        }
+        added
+    }
+
+    pub fn remove_relation<R: Relation>(&mut self, source: EntityId, target: EntityId) -> bool {
+        let removed = self.relations.remove(TypeId::of::<R>(), source, target);
+        if removed && self.history.is_recording() {
+            let edit = Edit::SetRelation { kind: TypeId::of::<R>(), source, target, added: false };
+            self.history.push("remove relation", Some(edit));
+        }
+        removed
+    }
+
+    pub fn has_relation<R: Relation>(&self, source: EntityId, target: EntityId) -> bool {
+        self.relations.contains(TypeId::of::<R>(), source, target)
+    }
+
+    // The entities the source links to, in the order the pairs were added
+    pub fn get_relation_targets<R: Relation>(&self, source: EntityId) -> &[EntityId] {
+        self.relations.get_targets(TypeId::of::<R>(), source)
+    }
+
+    // The entities that link to the target, in the order the pairs were added
+    pub fn get_relation_sources<R: Relation>(&self, target: EntityId) -> &[EntityId] {
+        self.relations.get_sources(TypeId::of::<R>(), target)
+    }
+
+    // Every (source, target) pair of the relation in no particular order
+    pub fn get_relation_pairs<R: Relation>(&self) -> impl Iterator<Item = (EntityId, EntityId)> + '_ {
+        self.relations.get_pairs(TypeId::of::<R>())
+    }
+
+    pub fn create_entity(&mut self) -> EntityId {
+        let name: String = String::from("Entity ") + &((self.last_id + 1).to_string());
+        self.create_entity_with_name(name)
+    }
+
+    pub fn create_entity_with_name(&mut self, name: String) -> EntityId {
+        self.last_id += 1;
+        self.entity_count += 1;
+        let id = self.allocate_id();
+        let row = self.archetypes[0].push_entity(id);
+        self.entity_names.entry(name.clone()).or_default().push(id);
+        let slot = &mut self.slots[id.get_index() as usize];
+        slot.entity = Some(Entity::new(id, name));
+        slot.location = EntityLocation { archetype: 0, row };
+        if self.history.is_recording() {
+            let name = self.get_entity_by_id(id).unwrap().get_name();
+            self.history.push("create entity", Some(Edit::CreateEntity { id, name }));
+        }
+        id
+    }
+
+    // Destroys the entity together with all of its descendants.
+    // Observers run once the whole tree is gone, descendants first.
+    pub fn destroy_entity(&mut self, id: EntityId) -> bool {
+        if !self.contains_entity(id) {
+            return false;
+        }
+        if self.history.is_recording() {
+            let edit = DestroyedEntities::take(self, id).map(Edit::DestroyEntity);
+            self.without_recording(|scene| scene.destroy_entity(id));
+            self.history.push("destroy entity", edit);
+            return true;
+        }
+        let mut triggers = vec![];
+        if let Some(parent) = self.extract_component::<Parent>(id).map(|x| x.get()) {
+            triggers.push((TriggerKind::Remove(TypeId::of::<Parent>()), id));
+            let children = self.get_component_mut::<Children>(parent).unwrap();
+            children.remove(id);
+            if children.get().is_empty() {
+                self.extract_component::<Children>(parent);
+                triggers.push((TriggerKind::Remove(TypeId::of::<Children>()), parent));
+            }
+        }
+        for id in self.get_tree(id).into_iter().rev() {
+            for type_id in self.despawn(id) {
+                triggers.push((TriggerKind::Remove(type_id), id));
+            }
+            triggers.push((TriggerKind::Despawn, id));
+        }
+        for (kind, id) in triggers {
+            self.trigger(kind, id);
+        }
+        true
+    }
+
+    // Frees the entity and drops its components without triggering observers. Returns the types of the components.
+    fn despawn(&mut self, id: EntityId) -> Vec<TypeId> {
+        let location = self.get_location(id).unwrap();
+        let component_types: Vec<TypeId> = self.get_component_types(id).into_iter().map(|(x, _)| x).collect();
+        for type_id in component_types.iter() {
+            self.record_removed(id, *type_id);
+        }
+        self.archetypes[location.archetype].on_entity_destroyed(location.row, id);
+        self.sparse_sets.on_entity_destroyed(id);
+        let moved = self.archetypes[location.archetype].remove_row(location.row);
+        self.relocate(moved, location);
+        let slot = &mut self.slots[id.get_index() as usize];
+        let entity = slot.entity.take().unwrap();
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_indices.push(id.get_index());
+        self.entity_count -= 1;
+        self.unindex_name(&entity.get_name(), id);
+        self.relations.remove_entity(id);
+        component_types
+    }
+
+    // The entity followed by all of its descendants, parents before their children
+    pub(crate) fn get_tree(&self, id: EntityId) -> Vec<EntityId> {
+        let mut ids = vec![id];
+        let mut next = 0;
+        while next < ids.len() {
+            ids.extend_from_slice(self.get_children(ids[next]));
+            next += 1;
+        }
+        ids
+    }
+
+    // Moves the entity and its descendants to the other scene under new ids, keeping their names, components
+    // and hierarchy. The entity is detached from its parent first and relations to entities that stay are dropped.
+    // Change detection sees the components as removed here and added there. Component hooks and observers
+    // do not run since no component is created or dropped.
+    pub fn move_entity_to(&mut self, other: &mut Scene, id: EntityId) -> EntityMap {
+        if !self.contains_entity(id) {
+            return EntityMap::new();
+        }
+        self.without_recording(|scene| scene.remove_parent(id));
+        let ids = self.get_tree(id);
+        self.transfer_entities(other, &ids)
+    }
+
+    // Moves all entities of the other scene into this one, see move_entity_to.
+    // The resources, systems and observers of the other scene are dropped with it.
+    pub fn merge(&mut self, mut other: Scene) -> EntityMap {
+        let ids: Vec<EntityId> = other.get_entities().map(|x| x.get_id()).collect();
+        other.transfer_entities(self, &ids)
+    }
+
+    // The entities must include all descendants of each of them. Neither history records the move.
+    fn transfer_entities(&mut self, other: &mut Scene, ids: &[EntityId]) -> EntityMap {
+        self.history.suspend();
+        other.history.suspend();
+        let map = self.transfer_entities_unrecorded(other, ids);
+        self.history.resume();
+        other.history.resume();
+        map
+    }
+
+    fn transfer_entities_unrecorded(&mut self, other: &mut Scene, ids: &[EntityId]) -> EntityMap {
+        let mut map = EntityMap::new();
+        for id in ids.iter().copied() {
+            let name = self.get_entity_by_id(id).unwrap().get_name();
+            map.insert(id, other.create_entity_with_name(name));
+        }
+        let tick = other.get_change_tick();
+        for id in ids.iter().copied() {
+            let new_id = map.get(id).unwrap();
+            let created = other.get_location(new_id).unwrap();
+            let moved = other.archetypes[created.archetype].remove_row(created.row);
+            other.relocate(moved, created);
+
+            let component_types = self.get_component_types(id);
+            self.sparse_sets.transfer(id, &mut other.sparse_sets, new_id, tick);
+            let location = self.get_location(id).unwrap();
+            let archetype = &mut self.archetypes[location.archetype];
+            let target = other.get_archetype_like(archetype);
+            let (row, moved) = archetype.transfer_row(location.row, &mut other.archetypes[target], new_id, tick);
+            other.slots[new_id.get_index() as usize].location = EntityLocation { archetype: target, row };
+            self.relocate(moved, location);
+
+            for (type_id, _) in component_types {
+                self.record_removed(id, type_id);
+            }
+            let slot = &mut self.slots[id.get_index() as usize];
+            let entity = slot.entity.take().unwrap();
+            slot.generation = slot.generation.wrapping_add(1);
+            self.free_indices.push(id.get_index());
+            self.entity_count -= 1;
+            self.unindex_name(&entity.get_name(), id);
+        }
+        for (_, new_id) in map.iter() {
+            if let Some(parent) = other.get_component_mut::<Parent>(new_id) {
+                *parent = Parent::new(map.get(parent.get()).unwrap());
+            }
+            if let Some(children) = other.get_component_mut::<Children>(new_id) {
+                let mut mapped = Children::new();
+                for child in children.get() {
+                    mapped.push(map.get(*child).unwrap());
+                }
+                *children = mapped;
+            }
+        }
+        self.relations.copy_mapped(&mut other.relations, &map);
+        for id in ids.iter().copied() {
+            self.relations.remove_entity(id);
+        }
+        map
+    }
+
+    // Destroys all entities and forgets their ids
+    pub(crate) fn clear_entities(&mut self) {
+        let ids: Vec<EntityId> = self.get_entities().map(|x| x.get_id()).collect();
+        for id in ids {
+            self.destroy_entity(id);
+        }
+        self.slots.clear();
+        self.free_indices.clear();
+        self.last_id = 0;
+    }
+
+    // Creates an entity with the given id. Returns false if the index is in use.
+    // The index is left in the free list, allocate_id skips it while it is taken.
+    pub(crate) fn restore_entity(&mut self, id: EntityId, name: String) -> bool {
+        let index = id.get_index() as usize;
+        while self.slots.len() <= index {
+            self.free_indices.push(self.slots.len() as u32);
+            self.slots.push(EntitySlot {
+                generation: 0,
+                entity: None,
+                location: EntityLocation { archetype: 0, row: 0 },
+            });
+        }
+        if self.slots[index].entity.is_some() {
+            return false;
+        }
+        self.last_id += 1;
+        self.entity_count += 1;
+        let row = self.archetypes[0].push_entity(id);
+        self.entity_names.entry(name.clone()).or_default().push(id);
+        let slot = &mut self.slots[index];
+        slot.generation = id.get_generation();
+        slot.entity = Some(Entity::new(id, name));
+        slot.location = EntityLocation { archetype: 0, row };
+        true
+    }
+
+    pub(crate) fn get_entity_ids(&self) -> EntityIds {
+        EntityIds {
+            generations: self.slots.iter().map(|x| x.generation).collect(),
+            free_indices: self.free_indices.clone(),
+            entity_names: self.entity_names.clone(),
+            last_id: self.last_id,
+        }
+    }
+
+    // Destroys all entities and returns to the given ids. The entities that were alive
+    // have to be inserted again. Removals are forgotten as the destroyed entities never existed in that state.
+    pub(crate) fn reset_entity_ids(&mut self, ids: &EntityIds) {
+        self.clear_entities();
+        self.removed_components.clear();
+        self.slots = ids
+            .generations
             .iter()
-            .position(|x| x.borrow().get_id() == entity_id);
-        match index {
-            Some(i) => {
-                self.entities[i].borrow_mut().invalidate();
-                self.entities.remove(i);
-                true
+            .map(|generation| EntitySlot {
+                generation: *generation,
+                entity: None,
+                location: EntityLocation { archetype: 0, row: 0 },
+            })
+            .collect();
+        self.free_indices = ids.free_indices.clone();
+        self.entity_names = ids.entity_names.clone();
+        self.last_id = ids.last_id;
+    }
+
+    pub(crate) fn get_relations(&self) -> &Relations {
+        &self.relations
+    }
+
+    pub(crate) fn set_relations(&mut self, relations: Relations) {
+        self.relations = relations;
+    }
+
+    pub(crate) fn get_relations_mut(&mut self) -> &mut Relations {
+        &mut self.relations
+    }
+
+    // Inserts an entity of the ids given to reset_entity_ids
+    pub(crate) fn insert_entity(&mut self, id: EntityId, name: String) {
+        self.entity_count += 1;
+        let row = self.archetypes[0].push_entity(id);
+        let slot = &mut self.slots[id.get_index() as usize];
+        slot.entity = Some(Entity::new(id, name));
+        slot.location = EntityLocation { archetype: 0, row };
+    }
+
+    pub fn contains_entity(&self, id: EntityId) -> bool {
+        self.get_entity_by_id(id).is_some()
+    }
+
+    pub fn get_entity_by_id(&self, id: EntityId) -> Option<&Entity> {
+        match self.slots.get(id.get_index() as usize) {
+            Some(slot) if slot.generation == id.get_generation() => slot.entity.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn get_entity_ref_by_id(&self, id: EntityId) -> Option<EntityRef<'_>> {
+        if self.contains_entity(id) {
+            Some(EntityRef::new(self, id))
+        } else {
+            None
+        }
+    }
+
+    pub fn get_entity_mut_by_id(&mut self, id: EntityId) -> Option<EntityMut<'_>> {
+        if self.contains_entity(id) {
+            Some(EntityMut::new(self, id))
+        } else {
+            None
+        }
+    }
+
+    pub fn set_entity_name(&mut self, id: EntityId, name: String) -> bool {
+        if !self.contains_entity(id) {
+            return false;
+        }
+        let entity = self.slots[id.get_index() as usize].entity.as_mut().unwrap();
+        let old_name = entity.get_name();
+        if old_name == name {
+            return true;
+        }
+        entity.set_name(name.clone());
+        self.unindex_name(&old_name, id);
+        if self.history.is_recording() {
+            let edit = Edit::SetName { id, old: old_name, new: name.clone() };
+            self.history.push("rename entity", Some(edit));
+        }
+        self.entity_names.entry(name).or_default().push(id);
+        true
+    }
+
+    // Returns the entity that was given the name first when several share it
+    pub fn get_entity_by_name(&self, name: &str) -> Option<EntityId> {
+        self.entity_names.get(name).and_then(|x| x.get_first())
+    }
+
+    // In the order the entities were given the name
+    pub fn get_entities_by_name(&self, name: &str) -> impl Iterator<Item = EntityId> + '_ {
+        self.entity_names.get(name).into_iter().flat_map(|x| x.iter())
+    }
+
+    pub fn get_entities(&self) -> impl Iterator<Item = &Entity> {
+        self.slots.iter().filter_map(|slot| slot.entity.as_ref())
+    }
+
+    // The number of entity indices that were ever allocated
+    pub(crate) fn get_slot_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn get_entity_count(&self) -> usize {
+        self.entity_count
+    }
+
+    pub fn add_component<T: Component + 'static>(&mut self, id: EntityId, component: T) -> bool {
+        if self.history.is_recording() && self.contains_entity(id) {
+            let type_id = TypeId::of::<T>();
+            let old = self.has_component::<T>(id).then(|| history::copy_component(self, id, type_id));
+            self.without_recording(|scene| scene.add_component(id, component));
+            let new = history::copy_component(self, id, type_id);
+            let edit = match old {
+                Some(old) => old.zip(new).map(|(old, new)| Edit::ReplaceComponent { id, old, new }),
+                None => new.map(|new| Edit::AddComponent { id, new, remove: Scene::remove_component::<T> }),
+            };
+            self.history.push("add component", edit);
+            return true;
+        }
+        let replaced = match self.insert_component(id, component) {
+            Some(replaced) => replaced,
+            None => return false,
+        };
+        if replaced {
+            self.trigger(TriggerKind::Remove(TypeId::of::<T>()), id);
+        }
+        self.trigger(TriggerKind::Add(TypeId::of::<T>()), id);
+        true
+    }
+
+    pub fn remove_component<T: Component + 'static>(&mut self, id: EntityId) -> bool {
+        self.take_component::<T>(id).is_some()
+    }
+
+    // Taking Parent detaches the entity from its parent and taking Children detaches all of its children
+    pub fn take_component<T: Component + 'static>(&mut self, id: EntityId) -> Option<T> {
+        if serialization::is_hierarchy(TypeId::of::<T>()) {
+            return self.take_hierarchy_component::<T>(id);
+        }
+        if self.history.is_recording() && self.has_component::<T>(id) {
+            let old = history::copy_component(self, id, TypeId::of::<T>());
+            let removed = self.without_recording(|scene| scene.take_component::<T>(id));
+            let edit = old.map(|old| Edit::RemoveComponent { id, old, remove: Scene::remove_component::<T> });
+            self.history.push("remove component", edit);
+            return removed;
+        }
+        let removed = self.extract_component::<T>(id)?;
+        self.trigger(TriggerKind::Remove(TypeId::of::<T>()), id);
+        Some(removed)
+    }
+
+    fn take_hierarchy_component<T: Component + 'static>(&mut self, id: EntityId) -> Option<T> {
+        let removed: Box<dyn Any> = if TypeId::of::<T>() == TypeId::of::<Parent>() {
+            let parent = self.get_parent(id)?;
+            self.remove_parent(id);
+            Box::new(Parent::new(parent))
+        } else {
+            let children = self.get_component::<Children>(id)?.get().to_vec();
+            let mut removed = Children::new();
+            for child in children {
+                self.remove_parent(child);
+                removed.push(child);
+            }
+            Box::new(removed)
+        };
+        removed.downcast::<T>().ok().map(|x| *x)
+    }
+
+    // Adds the component without triggering observers. Returns whether a component
+    // of the same type was replaced, or None if the entity does not exist.
+    fn insert_component<T: Component + 'static>(&mut self, id: EntityId, component: T) -> Option<bool> {
+        let location = self.get_location(id)?;
+        let tick = self.get_change_tick();
+        if T::STORAGE == StorageType::SparseSet {
+            let set = self.sparse_sets.get_or_insert::<T>();
+            let replaced = set.insert(id, component, tick);
+            let component = set.get_mut(id).unwrap().0;
+            if let Some(mut replaced) = replaced {
+                replaced.on_remove(id);
+                component.on_add(id);
+                return Some(true);
             }
+            component.on_add(id);
+            return Some(false);
+        }
+        if let Some((column, ticks)) = self.archetypes[location.archetype].get_column_mut::<T>() {
+            let mut replaced = std::mem::replace(&mut column[location.row], component);
+            ticks[location.row] = ComponentTicks::new(tick);
+            replaced.on_remove(id);
+            column[location.row].on_add(id);
+            return Some(true);
+        }
+        let target = self.get_add_target::<T>(location.archetype);
+        let (source_archetype, target_archetype) = get_two_mut(&mut self.archetypes, location.archetype, target);
+        let (_, moved) = source_archetype.move_row::<T>(location.row, target_archetype);
+        target_archetype.push_component(component, tick);
+        let row = target_archetype.push_entity(id);
+        self.relocate(moved, location);
+        self.slots[id.get_index() as usize].location = EntityLocation { archetype: target, row };
+        self.get_component_mut::<T>(id).unwrap().on_add(id);
+        Some(false)
+    }
+
+    // Removes the component without triggering observers
+    fn extract_component<T: Component + 'static>(&mut self, id: EntityId) -> Option<T> {
+        if T::STORAGE == StorageType::SparseSet {
+            let (mut removed, _) = self.sparse_sets.get_mut::<T>()?.remove(id)?;
+            self.record_removed(id, TypeId::of::<T>());
+            removed.on_remove(id);
+            return Some(removed);
+        }
+        let location = match self.get_location(id) {
+            Some(location) if self.archetypes[location.archetype].has_component(TypeId::of::<T>()) => location,
+            _ => return None,
+        };
+        let target = self.get_remove_target(location.archetype, TypeId::of::<T>());
+        let (source_archetype, target_archetype) = get_two_mut(&mut self.archetypes, location.archetype, target);
+        let (removed, moved) = source_archetype.move_row::<T>(location.row, target_archetype);
+        let row = target_archetype.push_entity(id);
+        self.relocate(moved, location);
+        self.slots[id.get_index() as usize].location = EntityLocation { archetype: target, row };
+        self.record_removed(id, TypeId::of::<T>());
+        let mut removed = removed.unwrap();
+        removed.on_remove(id);
+        Some(removed)
+    }
+
+    pub fn has_component<T: Component + 'static>(&self, id: EntityId) -> bool {
+        if T::STORAGE == StorageType::SparseSet {
+            return self.sparse_sets.get::<T>().is_some_and(|x| x.get(id).is_some());
+        }
+        match self.get_location(id) {
+            Some(location) => self.archetypes[location.archetype].has_component(TypeId::of::<T>()),
             None => false,
         }
     }
 
-    pub fn get_entity_by_id(&self, id: u64) -> Option<Rc<RefCell<Entity>>> {
-        let found = self.entities.iter().find(|x| x.borrow().get_id() == id);
-        match found {
-            Some(p) => Option::Some(p.clone()),
-            None => Option::None,
+    // Returns the type ids and type names of all components of the entity,
+    // first the table components in type id order and then the sparse set components
+    pub fn get_component_types(&self, id: EntityId) -> Vec<(TypeId, &'static str)> {
+        match self.get_location(id) {
+            Some(location) => {
+                let archetype = &self.archetypes[location.archetype];
+                let names = archetype.get_component_type_names();
+                let mut types: Vec<(TypeId, &'static str)> = archetype.get_component_types().iter().copied().zip(names).collect();
+                types.extend(self.sparse_sets.get_component_types(id));
+                types
+            }
+            None => vec![],
         }
     }
 
-    pub fn get_entity_ref_by_id(&self, id: u64) -> Option<std::cell::Ref<Entity>> {
-        let found = self.entities.iter().find(|x| x.borrow().get_id() == id);
-        match found {
-            Some(p) => Some((*p).borrow()),
-            None => Option::None,
+    pub fn get_component<T: Component + 'static>(&self, id: EntityId) -> Option<&T> {
+        if T::STORAGE == StorageType::SparseSet {
+            return self.sparse_sets.get::<T>()?.get(id);
         }
+        let location = self.get_location(id)?;
+        self.archetypes[location.archetype].get_column::<T>()?.get(location.row)
     }
 
-    pub fn get_entity_mut_by_id(&self, id: u64) -> Option<std::cell::RefMut<Entity>> {
-        let found = self.entities.iter().find(|x| x.borrow().get_id() == id);
-        match found {
-            Some(p) => Some((*p).borrow_mut()),
-            None => Option::None,
+    pub fn get_component_ticks<T: Component + 'static>(&self, id: EntityId) -> Option<ComponentTicks> {
+        if T::STORAGE == StorageType::SparseSet {
+            return self.sparse_sets.get::<T>()?.get_ticks(id);
         }
+        let location = self.get_location(id)?;
+        self.archetypes[location.archetype].get_ticks::<T>()?.get(location.row).copied()
     }
 
-    pub fn get_entity_by_name(&self, name: String) -> Option<Rc<RefCell<Entity>>> {
-        let found = self.entities.iter().find(|x| x.borrow().get_name() == name);
-        match found {
-            Some(p) => Option::Some(p.clone()),
-            None => Option::None,
+    // Marks the component as changed
+    pub fn get_component_mut<T: Component + 'static>(&mut self, id: EntityId) -> Option<&mut T> {
+        let tick = self.get_change_tick();
+        if T::STORAGE == StorageType::SparseSet {
+            let (component, ticks) = self.sparse_sets.get_mut::<T>()?.get_mut(id)?;
+            ticks.set_changed(tick);
+            return Some(component);
         }
+        let location = self.get_location(id)?;
+        let (column, ticks) = self.archetypes[location.archetype].get_column_mut::<T>()?;
+        ticks[location.row].set_changed(tick);
+        column.get_mut(location.row)
     }
 
-    pub fn get_entities(&self) -> &Vec<Rc<RefCell<Entity>>> {
-        &self.entities
+    // The caller must have exclusive access to T for the lifetime of the returned reference.
+    // Marks the component as changed with the given tick.
+    #[allow(clippy::mut_from_ref)]
+    pub(crate) unsafe fn get_component_unchecked_mut<T: Component + 'static>(&self, id: EntityId, tick: u64) -> Option<&mut T> {
+        let (component, ticks) = if T::STORAGE == StorageType::SparseSet {
+            self.sparse_sets.get::<T>()?.get_ptr(id)?
+        } else {
+            let location = self.get_location(id)?;
+            let (column, ticks) = self.archetypes[location.archetype].get_column_ptr::<T>()?;
+            (column.add(location.row), ticks.add(location.row))
+        };
+        (*ticks).set_changed(tick);
+        Some(&mut *component)
     }
 
-    pub fn get_entity_count(&self) -> usize {
-        self.entities.len()
+    // Returns the components of the entity that are registered for reflection
+    pub fn get_reflected_components(&self, id: EntityId) -> Vec<&dyn Reflect> {
+        let registry = &self.component_registry;
+        let types = self.get_component_types(id);
+        types
+            .into_iter()
+            .filter_map(|(type_id, _)| registry.get_reflect(type_id)?(self, id))
+            .collect()
+    }
+
+    // Reads a field of a reflected component by a path like "CountComponent.count"
+    pub fn get_field_text(&self, id: EntityId, path: &str) -> Result<String, ReflectError> {
+        reflect::get_field_text(self, id, path)
+    }
+
+    // Writes a field of a reflected component by a path like "CountComponent.count" and marks the component changed
+    // The history records a copy of the whole component from before and after the write.
+    pub fn set_field_text(&mut self, id: EntityId, path: &str, text: &str) -> Result<(), ReflectError> {
+        let type_id = match reflect::get_component_type(self, id, path) {
+            Some(type_id) if self.history.is_recording() => type_id,
+            _ => return reflect::set_field_text(self, id, path, text),
+        };
+        let old = history::copy_component(self, id, type_id);
+        self.without_recording(|scene| reflect::set_field_text(scene, id, path, text))?;
+        let new = history::copy_component(self, id, type_id);
+        let edit = old.zip(new).map(|(old, new)| Edit::ReplaceComponent { id, old, new });
+        self.history.push("set field", edit);
+        Ok(())
+    }
+
+    // Lists all entities with their components, showing the field values of reflected components
+    pub fn dump(&self) -> String {
+        reflect::dump(self)
+    }
+
+    // Returns false if either entity does not exist or the parent is the child or one of its descendants
+    pub fn set_parent(&mut self, child: EntityId, parent: EntityId) -> bool {
+        if !self.contains_entity(child) || !self.contains_entity(parent) {
+            return false;
+        }
+        let mut ancestor = Some(parent);
+        while let Some(id) = ancestor {
+            if id == child {
+                return false;
+            }
+            ancestor = self.get_parent(id);
+        }
+        if self.history.is_recording() {
+            let old = self.get_parent(child).map(|x| (x, self.get_children(x).to_vec()));
+            self.without_recording(|scene| scene.set_parent(child, parent));
+            let edit = Edit::SetParent { child, old, new: Some(parent) };
+            self.history.push("set parent", Some(edit));
+            return true;
+        }
+        self.remove_parent(child);
+        self.insert_component(child, Parent::new(parent));
+        let children_added = !self.has_component::<Children>(parent);
+        if children_added {
+            self.insert_component(parent, Children::new());
+        }
+        self.get_component_mut::<Children>(parent).unwrap().push(child);
+        if let Some(transform) = self.get_component_mut::<Transform>(child) {
+            transform.mark_changed();
+        }
+        self.trigger(TriggerKind::Add(TypeId::of::<Parent>()), child);
+        if children_added {
+            self.trigger(TriggerKind::Add(TypeId::of::<Children>()), parent);
+        }
+        true
+    }
+
+    // Makes the entity a root again. Returns false if it had no parent.
+    pub fn remove_parent(&mut self, child: EntityId) -> bool {
+        if self.history.is_recording() {
+            let old = match self.get_parent(child) {
+                Some(parent) => (parent, self.get_children(parent).to_vec()),
+                None => return false,
+            };
+            self.without_recording(|scene| scene.remove_parent(child));
+            let edit = Edit::SetParent { child, old: Some(old), new: None };
+            self.history.push("remove parent", Some(edit));
+            return true;
+        }
+        let parent = match self.extract_component::<Parent>(child) {
+            Some(parent) => parent.get(),
+            None => return false,
+        };
+        let children = self.get_component_mut::<Children>(parent).unwrap();
+        children.remove(child);
+        let children_removed = children.get().is_empty();
+        if children_removed {
+            self.extract_component::<Children>(parent);
+        }
+        if let Some(transform) = self.get_component_mut::<Transform>(child) {
+            transform.mark_changed();
+        }
+        self.trigger(TriggerKind::Remove(TypeId::of::<Parent>()), child);
+        if children_removed {
+            self.trigger(TriggerKind::Remove(TypeId::of::<Children>()), parent);
+        }
+        true
+    }
+
+    pub fn get_parent(&self, id: EntityId) -> Option<EntityId> {
+        self.get_component::<Parent>(id).map(|x| x.get())
+    }
+
+    pub fn get_children(&self, id: EntityId) -> &[EntityId] {
+        match self.get_component::<Children>(id) {
+            Some(children) => children.get(),
+            None => &[],
+        }
+    }
+
+    // Writes the GlobalTransform of every entity whose Transform or ancestors changed.
+    // Returns the number of global transforms that were written.
+    pub fn propagate_transforms(&mut self) -> usize {
+        transform::propagate_transforms(self)
+    }
+
+    pub fn query<Q: Fetch>(&mut self) -> QueryIter<'_, Q> {
+        self.query_filtered::<Q, ()>()
+    }
+
+    pub fn query_filtered<Q: Fetch, F: QueryFilter>(&mut self) -> QueryIter<'_, Q, F> {
+        // Borrowing the scene mutably keeps the columns exclusive while the query is alive
+        unsafe { self.query_unchecked::<Q, F>(self.get_scene_ticks()) }
+    }
+
+    // The caller must have exclusive access to the components Q writes and
+    // shared access to the components Q reads for the lifetime of the iterator
+    pub(crate) unsafe fn query_unchecked<Q: Fetch, F: QueryFilter>(&self, ticks: Ticks) -> QueryIter<'_, Q, F> {
+        QueryIter::new(&self.archetypes, &self.sparse_sets, ticks)
+    }
+
+    pub fn get_archetypes(&self) -> &[Archetype] {
+        &self.archetypes
+    }
+
+    fn get_location(&self, id: EntityId) -> Option<EntityLocation> {
+        if self.contains_entity(id) {
+            Some(self.slots[id.get_index() as usize].location)
+        } else {
+            None
+        }
+    }
+
+    // Points the entity that was swapped into the freed row to its new location
+    fn relocate(&mut self, moved: Option<EntityId>, location: EntityLocation) {
+        if let Some(moved) = moved {
+            self.slots[moved.get_index() as usize].location = location;
+        }
+    }
+
+    fn get_add_target<T: Component + 'static>(&mut self, archetype: usize) -> usize {
+        let type_id = TypeId::of::<T>();
+        if let Some(target) = self.archetypes[archetype].get_add_edge(type_id) {
+            return target;
+        }
+        let mut component_types = self.archetypes[archetype].get_component_types().to_vec();
+        component_types.push(type_id);
+        component_types.sort();
+        let target = match self.archetype_ids.get(&component_types) {
+            Some(target) => *target,
+            None => {
+                let new_archetype = self.archetypes[archetype].with_added::<T>();
+                self.push_archetype(component_types, new_archetype)
+            }
+        };
+        self.archetypes[archetype].set_add_edge(type_id, target);
+        self.archetypes[target].set_remove_edge(type_id, archetype);
+        target
+    }
+
+    fn get_remove_target(&mut self, archetype: usize, type_id: TypeId) -> usize {
+        if let Some(target) = self.archetypes[archetype].get_remove_edge(type_id) {
+            return target;
+        }
+        let mut component_types = self.archetypes[archetype].get_component_types().to_vec();
+        component_types.retain(|x| *x != type_id);
+        let target = match self.archetype_ids.get(&component_types) {
+            Some(target) => *target,
+            None => {
+                let new_archetype = self.archetypes[archetype].with_removed(type_id);
+                self.push_archetype(component_types, new_archetype)
+            }
+        };
+        self.archetypes[archetype].set_remove_edge(type_id, target);
+        self.archetypes[target].set_add_edge(type_id, archetype);
+        target
+    }
+
+    fn trigger(&mut self, kind: TriggerKind, entity: EntityId) {
+        for id in self.observers.get_matching(kind) {
+            if let Some(mut callback) = self.observers.take(id) {
+                callback(entity, self);
+                self.observers.put_back(id, callback);
+            }
+        }
+    }
+
+    fn unindex_name(&mut self, name: &str, id: EntityId) {
+        if let Some(ids) = self.entity_names.get_mut(name) {
+            if !ids.remove(id) {
+                self.entity_names.remove(name);
+            }
+        }
+    }
+
+    // Finds or creates the archetype with the component types of an archetype from another scene
+    fn get_archetype_like(&mut self, archetype: &Archetype) -> usize {
+        let component_types = archetype.get_component_types().to_vec();
+        match self.archetype_ids.get(&component_types) {
+            Some(target) => *target,
+            None => self.push_archetype(component_types, archetype.empty_copy()),
+        }
+    }
+
+    fn push_archetype(&mut self, component_types: Vec<TypeId>, archetype: Archetype) -> usize {
+        self.archetypes.push(archetype);
+        self.archetype_ids.insert(component_types, self.archetypes.len() - 1);
+        self.archetypes.len() - 1
+    }
+
+    // Indices taken by restore_entity stay in the free list and are skipped here
+    fn allocate_id(&mut self) -> EntityId {
+        while let Some(index) = self.free_indices.pop() {
+            let slot = &self.slots[index as usize];
+            if slot.entity.is_none() {
+                return EntityId::new(index, slot.generation);
+            }
+        }
+        self.slots.push(EntitySlot {
+            generation: 0,
+            entity: None,
+            location: EntityLocation { archetype: 0, row: 0 },
+        });
+        EntityId::new((self.slots.len() - 1) as u32, 0)
+    }
+}
+
+fn get_two_mut(archetypes: &mut [Archetype], first: usize, second: usize) -> (&mut Archetype, &mut Archetype) {
+    if first < second {
+        let (left, right) = archetypes.split_at_mut(second);
+        (&mut left[first], &mut right[0])
+    } else {
+        let (left, right) = archetypes.split_at_mut(first);
+        (&mut right[0], &mut left[second])
     }
 }
 
@@ -106,7 +1332,7 @@ mod tests {
             scene.create_entity();
         }
         assert_eq!(entity_count, scene.get_entity_count());
-        assert_eq!(entity_count, scene.get_entities().len());
+        assert_eq!(entity_count, scene.get_entities().count());
     }
 
     #[test]
@@ -116,15 +1342,15 @@ mod tests {
         let cat_name = String::from("cat");
         scene.create_entity_with_name(dog_name.clone());
         scene.create_entity_with_name(cat_name.clone());
-        scene.get_entity_by_name(dog_name).unwrap();
-        scene.get_entity_by_name(cat_name).unwrap();
+        scene.get_entity_by_name(&dog_name).unwrap();
+        scene.get_entity_by_name(&cat_name).unwrap();
     }
 
     #[test]
     #[should_panic]
     fn get_nonexisting_entity_by_name() {
         let scene = Scene::new();
-        scene.get_entity_by_name(String::from("mouse")).unwrap();
+        scene.get_entity_by_name("mouse").unwrap();
     }
 
     #[test]
@@ -135,9 +1361,67 @@ mod tests {
         assert_eq!(1, scene.get_entity_count());
         let cat_entity = scene.create_entity_with_name(String::from("cat"));
         assert_eq!(2, scene.get_entity_count());
-        scene.destroy_entity(&dog_entity);
+        scene.destroy_entity(dog_entity);
         assert_eq!(1, scene.get_entity_count());
-        scene.destroy_entity(&cat_entity);
+        scene.destroy_entity(cat_entity);
         assert_eq!(0, scene.get_entity_count());
     }
+
+    #[test]
+    fn stale_id_after_destroy() {
+        let mut scene = Scene::new();
+        let dog_entity = scene.create_entity_with_name(String::from("dog"));
+        assert!(scene.destroy_entity(dog_entity));
+        assert!(!scene.destroy_entity(dog_entity));
+        assert!(scene.get_entity_by_id(dog_entity).is_none());
+        assert!(scene.get_entity_mut_by_id(dog_entity).is_none());
+    }
+
+    #[test]
+    fn reuse_destroyed_index() {
+        let mut scene = Scene::new();
+        let dog_entity = scene.create_entity_with_name(String::from("dog"));
+        scene.destroy_entity(dog_entity);
+        let cat_entity = scene.create_entity_with_name(String::from("cat"));
+        assert_eq!(dog_entity.get_index(), cat_entity.get_index());
+        assert_ne!(dog_entity, cat_entity);
+        assert!(scene.get_entity_by_id(dog_entity).is_none());
+        assert_eq!("cat", scene.get_entity_by_id(cat_entity).unwrap().get_name());
+    }
+
+    #[test]
+    fn name_index_follows_renames_and_destroys() {
+        let mut scene = Scene::new();
+        let first_dog = scene.create_entity_with_name(String::from("dog"));
+        let second_dog = scene.create_entity_with_name(String::from("dog"));
+        assert_eq!(vec![first_dog, second_dog], scene.get_entities_by_name("dog").collect::<Vec<_>>());
+        assert_eq!(Some(first_dog), scene.get_entity_by_name("dog"));
+
+        assert!(scene.set_entity_name(first_dog, String::from("wolf")));
+        assert_eq!(Some(second_dog), scene.get_entity_by_name("dog"));
+        assert_eq!(Some(first_dog), scene.get_entity_by_name("wolf"));
+
+        scene.destroy_entity(second_dog);
+        assert!(scene.get_entities_by_name("dog").next().is_none());
+        assert!(!scene.set_entity_name(second_dog, String::from("ghost")));
+        assert_eq!(None, scene.get_entity_by_name("ghost"));
+    }
+
+    #[test]
+    fn name_index_keeps_its_order() {
+        let mut scene = Scene::new();
+        let cats: Vec<EntityId> = (0..5).map(|_| scene.create_entity_with_name(String::from("cat"))).collect();
+        assert!(scene.set_entity_name(cats[0], String::from("cat")));
+        scene.destroy_entity(cats[2]);
+        scene.destroy_entity(cats[1]);
+        scene.destroy_entity(cats[3]);
+        let late_cat = scene.create_entity_with_name(String::from("cat"));
+        assert_eq!(
+            vec![cats[0], cats[4], late_cat],
+            scene.get_entities_by_name("cat").collect::<Vec<_>>()
+        );
+
+        scene.destroy_entity(cats[0]);
+        assert_eq!(Some(cats[4]), scene.get_entity_by_name("cat"));
+    }
 }
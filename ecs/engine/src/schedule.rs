@@ -0,0 +1,384 @@
+use crate::command::Commands;
+use crate::query::Access;
+use crate::query::Ticks;
+use crate::scene::Scene;
+use crate::state::State;
+use crate::state::States;
+use crate::system::System;
+use crate::system::SystemContext;
+use std::collections::BTreeSet;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Stage {
+    PreUpdate,
+    FixedUpdate,
+    Update,
+    PostUpdate,
+    Render,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 5] = [
+        Stage::PreUpdate,
+        Stage::FixedUpdate,
+        Stage::Update,
+        Stage::PostUpdate,
+        Stage::Render,
+    ];
+}
+
+// Decides before every run whether a system runs this time
+pub(crate) type RunCondition = Box<dyn Fn(&Scene) -> bool + Send + Sync>;
+
+// Only true while the state resource of S holds the given state
+pub(crate) fn in_state<S: States>(state: S) -> RunCondition {
+    Box::new(move |scene: &Scene| scene.get_resource::<State<S>>().is_some_and(|x| *x.get() == state))
+}
+
+struct ScheduledSystem {
+    name: String,
+    access: Access,
+    before: Vec<String>,
+    after: Vec<String>,
+    condition: Option<RunCondition>,
+    system: Box<dyn System>,
+    commands: Commands,
+    last_run: u64,
+}
+
+#[derive(Default)]
+struct StageSystems {
+    systems: Vec<ScheduledSystem>,
+    plan: Option<StagePlan>,
+}
+
+// Systems in the same level have no ordering constraints or access conflicts
+// between them and can run at the same time
+struct StagePlan {
+    order: Vec<usize>,
+    levels: Vec<Vec<usize>>,
+    dependencies: Vec<(usize, usize, String)>,
+}
+
+impl StageSystems {
+    fn get_plan(&mut self, stage: Stage) -> &StagePlan {
+        if self.plan.is_none() {
+            self.plan = Some(self.build_plan(stage));
+        }
+        self.plan.as_ref().unwrap()
+    }
+
+    fn build_plan(&self, stage: Stage) -> StagePlan {
+        let (order, constraints) = self.sort(stage);
+        let mut level = vec![0; self.systems.len()];
+        let mut dependencies = vec![];
+        for (position, second) in order.iter().enumerate() {
+            for first in order[..position].iter() {
+                let mut reasons = vec![];
+                if constraints[*first].contains(second) {
+                    reasons.push(String::from("ordering"));
+                }
+                let conflicts = self.systems[*first].access.get_conflicts(&self.systems[*second].access);
+                if !conflicts.is_empty() {
+                    reasons.push(format!("conflict: {}", conflicts.join(", ")));
+                }
+                if !reasons.is_empty() {
+                    level[*second] = level[*second].max(level[*first] + 1);
+                    dependencies.push((*first, *second, reasons.join("; ")));
+                }
+            }
+        }
+
+        let level_count = level.iter().map(|x| x + 1).max().unwrap_or(0);
+        let mut levels = vec![vec![]; level_count];
+        for index in order.iter() {
+            levels[level[*index]].push(*index);
+        }
+        StagePlan { order, levels, dependencies }
+    }
+
+    // Topological sort of the ordering constraints. Ties are broken by the
+    // order in which the systems were added so the result is deterministic.
+    fn sort(&self, stage: Stage) -> (Vec<usize>, Vec<Vec<usize>>) {
+        let count = self.systems.len();
+        let mut edges: Vec<Vec<usize>> = vec![vec![]; count];
+        let mut incoming = vec![0; count];
+        for (index, system) in self.systems.iter().enumerate() {
+            for name in system.before.iter() {
+                let other = self.find_constraint(stage, system, name);
+                edges[index].push(other);
+                incoming[other] += 1;
+            }
+            for name in system.after.iter() {
+                let other = self.find_constraint(stage, system, name);
+                edges[other].push(index);
+                incoming[index] += 1;
+            }
+        }
+
+        let mut ready: BTreeSet<usize> = (0..count).filter(|x| incoming[*x] == 0).collect();
+        let mut order = Vec::with_capacity(count);
+        while let Some(index) = ready.pop_first() {
+            order.push(index);
+            for other in edges[index].iter() {
+                incoming[*other] -= 1;
+                if incoming[*other] == 0 {
+                    ready.insert(*other);
+                }
+            }
+        }
+
+        if order.len() < count {
+            let cycle: Vec<&str> = (0..count)
+                .filter(|x| incoming[*x] > 0)
+                .map(|x| self.systems[x].name.as_str())
+                .collect();
+            panic!("systems {} in stage {:?} have cyclic ordering constraints", cycle.join(", "), stage);
+        }
+        (order, edges)
+    }
+
+    fn find_constraint(&self, stage: Stage, system: &ScheduledSystem, name: &str) -> usize {
+        match self.systems.iter().position(|x| x.name == name) {
+            Some(index) => index,
+            None => panic!(
+                "system {} is ordered relative to {} which is not in stage {:?}",
+                system.name, name, stage
+            ),
+        }
+    }
+
+    fn record(&mut self, stage: Stage, parallel: bool) -> StageGraph {
+        self.get_plan(stage);
+        let plan = self.plan.as_ref().unwrap();
+        let mut levels = vec![0; self.systems.len()];
+        for (level, indices) in plan.levels.iter().enumerate() {
+            for index in indices.iter() {
+                levels[*index] = level;
+            }
+        }
+        let name = |index: usize| self.systems[index].name.clone();
+        StageGraph {
+            stage,
+            parallel,
+            systems: plan.order.iter().map(|x| (name(*x), levels[*x])).collect(),
+            dependencies: plan
+                .dependencies
+                .iter()
+                .map(|(first, second, reason)| (name(*first), name(*second), reason.clone()))
+                .collect(),
+        }
+    }
+}
+
+fn run_system(scene: &Scene, scheduled: &mut ScheduledSystem) {
+    let this_run = scene.increment_change_tick();
+    let ticks = Ticks::new(scheduled.last_run, this_run);
+    let mut context = SystemContext::new(scene, &scheduled.name, &scheduled.access, &mut scheduled.commands, ticks);
+    scheduled.system.run(&mut context);
+    scheduled.last_run = this_run;
+}
+
+struct StageGraph {
+    stage: Stage,
+    parallel: bool,
+    systems: Vec<(String, usize)>,
+    dependencies: Vec<(String, String, String)>,
+}
+
+// Records how the systems of one frame were scheduled and why some of them had to wait for others
+#[derive(Default)]
+pub struct ExecutionGraph {
+    stages: Vec<StageGraph>,
+}
+
+impl ExecutionGraph {
+    // Returns the systems of the stage grouped by the batches they ran in
+    pub fn get_batches(&self, stage: Stage) -> Vec<Vec<String>> {
+        let mut batches: Vec<Vec<String>> = vec![];
+        for graph in self.stages.iter().filter(|x| x.stage == stage) {
+            for (name, level) in graph.systems.iter() {
+                let batch = if graph.parallel { *level } else { batches.len() };
+                if batches.len() <= batch {
+                    batches.resize(batch + 1, vec![]);
+                }
+                batches[batch].push(name.clone());
+            }
+        }
+        batches
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph frame {\n");
+        for graph in self.stages.iter() {
+            dot += &format!("    subgraph cluster_{:?} {{\n", graph.stage);
+            dot += &format!("        label=\"{:?}\";\n", graph.stage);
+            for (name, level) in graph.systems.iter() {
+                dot += &format!("        \"{:?}/{}\" [label=\"{}\\nbatch {}\"];\n", graph.stage, name, name, level);
+            }
+            dot += "    }\n";
+            for (first, second, reason) in graph.dependencies.iter() {
+                dot += &format!(
+                    "    \"{:?}/{}\" -> \"{:?}/{}\" [label=\"{}\"];\n",
+                    graph.stage, first, graph.stage, second, reason
+                );
+            }
+        }
+        dot += "}\n";
+        dot
+    }
+}
+
+pub struct SystemConfig<'a> {
+    stage_systems: &'a mut StageSystems,
+    index: usize,
+}
+
+impl<'a> SystemConfig<'a> {
+    pub fn before(self, name: &str) -> SystemConfig<'a> {
+        self.stage_systems.systems[self.index].before.push(String::from(name));
+        self.stage_systems.plan = None;
+        self
+    }
+
+    pub fn after(self, name: &str) -> SystemConfig<'a> {
+        self.stage_systems.systems[self.index].after.push(String::from(name));
+        self.stage_systems.plan = None;
+        self
+    }
+
+    // Skips the system in frames where the condition is false. Skipped systems keep their
+    // place in the plan, so they still order and exclude the systems around them.
+    pub fn run_if<F: Fn(&Scene) -> bool + Send + Sync + 'static>(self, condition: F) -> SystemConfig<'a> {
+        self.stage_systems.systems[self.index].condition = Some(Box::new(condition));
+        self
+    }
+
+    pub fn run_in_state<S: States>(self, state: S) -> SystemConfig<'a> {
+        self.stage_systems.systems[self.index].condition = Some(in_state(state));
+        self
+    }
+}
+
+pub struct Schedule {
+    stages: Vec<StageSystems>,
+    parallel: bool,
+    execution_graph: Option<ExecutionGraph>,
+}
+
+impl Default for Schedule {
+    fn default() -> Schedule {
+        Schedule::new()
+    }
+}
+
+impl Schedule {
+    pub fn new() -> Schedule {
+        Schedule {
+            stages: Stage::ALL.iter().map(|_| StageSystems::default()).collect(),
+            parallel: true,
+            execution_graph: None,
+        }
+    }
+
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+
+    // In debug mode the execution graph of the last frame is kept for inspection
+    pub fn set_debug(&mut self, debug: bool) {
+        self.execution_graph = if debug { Some(ExecutionGraph::default()) } else { None };
+    }
+
+    pub fn get_execution_graph(&self) -> Option<&ExecutionGraph> {
+        self.execution_graph.as_ref()
+    }
+
+    pub(crate) fn begin_frame(&mut self) {
+        if let Some(graph) = self.execution_graph.as_mut() {
+            graph.stages.clear();
+        }
+    }
+
+    pub fn add_system<S: System + 'static>(&mut self, stage: Stage, system: S) -> SystemConfig<'_> {
+        let stage_systems = &mut self.stages[stage as usize];
+        let name = String::from(system.get_name());
+        if stage_systems.systems.iter().any(|x| x.name == name) {
+            panic!("system {} is already in stage {:?}", name, stage);
+        }
+        stage_systems.systems.push(ScheduledSystem {
+            name,
+            access: system.get_access(),
+            before: vec![],
+            after: vec![],
+            condition: None,
+            system: Box::new(system),
+            commands: Commands::new(),
+            last_run: 0,
+        });
+        stage_systems.plan = None;
+        let index = stage_systems.systems.len() - 1;
+        SystemConfig { stage_systems, index }
+    }
+
+    pub fn get_system_names(&mut self, stage: Stage) -> Vec<String> {
+        let stage_systems = &mut self.stages[stage as usize];
+        let order = stage_systems.get_plan(stage).order.clone();
+        order.into_iter().map(|x| stage_systems.systems[x].name.clone()).collect()
+    }
+
+    // Runs the systems of the stage and then applies their commands in the order the systems are sorted in
+    pub(crate) fn run_stage(&mut self, stage: Stage, scene: &mut Scene) {
+        let stage_systems = &mut self.stages[stage as usize];
+        // FixedUpdate can run several times per frame and is recorded once
+        if let Some(graph) = self.execution_graph.as_mut() {
+            if !graph.stages.iter().any(|x| x.stage == stage) {
+                graph.stages.push(stage_systems.record(stage, self.parallel));
+            }
+        }
+        let enabled: Vec<bool> = stage_systems
+            .systems
+            .iter()
+            .map(|x| x.condition.as_ref().is_none_or(|condition| condition(scene)))
+            .collect();
+        let plan = stage_systems.get_plan(stage);
+        let order = plan.order.clone();
+        let levels: Vec<Vec<usize>> = plan
+            .levels
+            .iter()
+            .map(|level| level.iter().copied().filter(|x| enabled[*x]).collect())
+            .collect();
+
+        if self.parallel {
+            run_levels(scene, &mut stage_systems.systems, levels);
+        } else {
+            for index in order.iter().filter(|x| enabled[**x]) {
+                run_system(scene, &mut stage_systems.systems[*index]);
+            }
+        }
+
+        for index in order {
+            scene.apply_commands(&mut stage_systems.systems[index].commands);
+        }
+    }
+}
+
+fn run_levels(scene: &Scene, systems: &mut [ScheduledSystem], levels: Vec<Vec<usize>>) {
+    for level in levels {
+        let mut systems: Vec<&mut ScheduledSystem> = systems
+            .iter_mut()
+            .enumerate()
+            .filter(|(index, _)| level.contains(index))
+            .map(|(_, x)| x)
+            .collect();
+        if systems.len() == 1 {
+            run_system(scene, systems[0]);
+            continue;
+        }
+        rayon::scope(|scope| {
+            for scheduled in systems.drain(..) {
+                scope.spawn(move |_| run_system(scene, scheduled));
+            }
+        });
+    }
+}
@@ -0,0 +1,493 @@
+use crate::entity::Component;
+use crate::entity::EntityId;
+use crate::hierarchy::Children;
+use crate::hierarchy::Parent;
+use crate::prefab::Prefab;
+use crate::prefab::PrefabComponent;
+use crate::reflect::Reflect;
+use crate::scene::Scene;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use std::any::Any;
+use std::any::TypeId;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+
+type SerializeFn = fn(&Scene, EntityId) -> Option<serde_json::Result<Value>>;
+type DeserializeFn = fn(&mut Scene, EntityId, Value) -> serde_json::Result<()>;
+type OverwriteFn = fn(&mut Scene, EntityId, Value) -> serde_json::Result<()>;
+type DecodeFn = fn(Value) -> serde_json::Result<PendingComponent>;
+// A deserialized component waiting to be added to an entity
+type PendingComponent = Box<dyn FnOnce(&mut Scene, EntityId)>;
+type CloneFn = fn(&Scene, EntityId) -> Option<Box<dyn Any + Send + Sync>>;
+type RestoreFn = fn(&mut Scene, EntityId, &(dyn Any + Send + Sync));
+pub(crate) type ReflectFn = fn(&Scene, EntityId) -> Option<&dyn Reflect>;
+pub(crate) type ReflectMutFn = fn(&mut Scene, EntityId) -> Option<&mut dyn Reflect>;
+
+#[derive(Clone)]
+struct RegistryEntry {
+    name: String,
+    type_id: TypeId,
+    serialize: SerializeFn,
+    deserialize: DeserializeFn,
+    overwrite: OverwriteFn,
+    decode: DecodeFn,
+}
+
+#[derive(Clone)]
+struct CloneEntry {
+    type_id: TypeId,
+    clone: CloneFn,
+    restore: RestoreFn,
+}
+
+#[derive(Clone)]
+struct ReflectEntry {
+    type_id: TypeId,
+    get: ReflectFn,
+    get_mut: ReflectMutFn,
+}
+
+// Maps the stable names used in saved scenes to the component types that opted into serialization.
+// Snapshots copy the components registered with register_clone, or else the serializable ones.
+// Components registered with register_reflect can be inspected by field paths.
+#[derive(Clone, Default)]
+pub struct ComponentRegistry {
+    entries: Vec<RegistryEntry>,
+    clone_entries: Vec<CloneEntry>,
+    reflect_entries: Vec<ReflectEntry>,
+}
+
+impl ComponentRegistry {
+    pub fn new() -> ComponentRegistry {
+        ComponentRegistry::default()
+    }
+
+    // Panics if the name or the type is already registered
+    pub fn register<T: Component + Serialize + DeserializeOwned + 'static>(&mut self, name: &str) {
+        if self.entries.iter().any(|x| x.name == name) {
+            panic!("component name {} is already registered", name);
+        }
+        if self.get_name(TypeId::of::<T>()).is_some() {
+            panic!("component {} is already registered", std::any::type_name::<T>());
+        }
+        self.entries.push(RegistryEntry {
+            name: String::from(name),
+            type_id: TypeId::of::<T>(),
+            serialize: serialize_component::<T>,
+            deserialize: deserialize_component::<T>,
+            overwrite: overwrite_component::<T>,
+            decode: decode_component::<T>,
+        });
+    }
+
+    // Panics if the type is already registered for cloning
+    pub fn register_clone<T: Component + Clone + 'static>(&mut self) {
+        if self.is_cloneable(TypeId::of::<T>()) {
+            panic!("component {} is already registered for cloning", std::any::type_name::<T>());
+        }
+        self.clone_entries.push(CloneEntry {
+            type_id: TypeId::of::<T>(),
+            clone: clone_component::<T>,
+            restore: restore_component::<T>,
+        });
+    }
+
+    pub fn is_cloneable(&self, type_id: TypeId) -> bool {
+        self.clone_entries.iter().any(|x| x.type_id == type_id)
+    }
+
+    // Panics if the type is already registered for reflection
+    pub fn register_reflect<T: Component + Reflect + 'static>(&mut self) {
+        if self.is_reflected(TypeId::of::<T>()) {
+            panic!("component {} is already registered for reflection", std::any::type_name::<T>());
+        }
+        self.reflect_entries.push(ReflectEntry {
+            type_id: TypeId::of::<T>(),
+            get: reflect_component::<T>,
+            get_mut: reflect_component_mut::<T>,
+        });
+    }
+
+    pub fn is_reflected(&self, type_id: TypeId) -> bool {
+        self.reflect_entries.iter().any(|x| x.type_id == type_id)
+    }
+
+    pub(crate) fn get_reflect(&self, type_id: TypeId) -> Option<ReflectFn> {
+        self.reflect_entries.iter().find(|x| x.type_id == type_id).map(|x| x.get)
+    }
+
+    pub(crate) fn get_reflect_mut(&self, type_id: TypeId) -> Option<ReflectMutFn> {
+        self.reflect_entries.iter().find(|x| x.type_id == type_id).map(|x| x.get_mut)
+    }
+
+    pub fn get_name(&self, type_id: TypeId) -> Option<&str> {
+        self.entries.iter().find(|x| x.type_id == type_id).map(|x| x.name.as_str())
+    }
+
+    fn get_by_type(&self, type_id: TypeId) -> Option<&RegistryEntry> {
+        self.entries.iter().find(|x| x.type_id == type_id)
+    }
+
+    fn get_by_name(&self, name: &str) -> Option<&RegistryEntry> {
+        self.entries.iter().find(|x| x.name == name)
+    }
+
+    // Returns None if the type is not registered or the entity has no such component.
+    // Saved values are decoded once, so a copy that could not be restored is an error,
+    // e.g. a NaN float that was saved as null.
+    pub(crate) fn copy_component(&self, scene: &Scene, id: EntityId, type_id: TypeId) -> Option<serde_json::Result<ComponentCopy>> {
+        if let Some(entry) = self.clone_entries.iter().find(|x| x.type_id == type_id) {
+            let value = (entry.clone)(scene, id)?;
+            return Some(Ok(ComponentCopy::Cloned(value, entry.restore)));
+        }
+        let entry = self.get_by_type(type_id)?;
+        let value = (entry.serialize)(scene, id)?;
+        Some(value.and_then(|x| (entry.decode)(x.clone()).map(|_| ComponentCopy::Saved(x, entry.deserialize))))
+    }
+}
+
+// A copy of one component that can be added to entities again
+pub(crate) enum ComponentCopy {
+    Cloned(Box<dyn Any + Send + Sync>, RestoreFn),
+    Saved(Value, DeserializeFn),
+}
+
+impl ComponentCopy {
+    pub(crate) fn restore(&self, scene: &mut Scene, id: EntityId) -> serde_json::Result<()> {
+        match self {
+            ComponentCopy::Cloned(value, restore) => {
+                restore(scene, id, value.as_ref());
+                Ok(())
+            }
+            ComponentCopy::Saved(value, deserialize) => deserialize(scene, id, value.clone()),
+        }
+    }
+}
+
+fn reflect_component<T: Component + Reflect + 'static>(scene: &Scene, id: EntityId) -> Option<&dyn Reflect> {
+    scene.get_component::<T>(id).map(|x| x as &dyn Reflect)
+}
+
+fn reflect_component_mut<T: Component + Reflect + 'static>(scene: &mut Scene, id: EntityId) -> Option<&mut dyn Reflect> {
+    scene.get_component_mut::<T>(id).map(|x| x as &mut dyn Reflect)
+}
+
+fn clone_component<T: Component + Clone + 'static>(scene: &Scene, id: EntityId) -> Option<Box<dyn Any + Send + Sync>> {
+    scene
+        .get_component::<T>(id)
+        .map(|x| Box::new(x.clone()) as Box<dyn Any + Send + Sync>)
+}
+
+fn restore_component<T: Component + Clone + 'static>(scene: &mut Scene, id: EntityId, value: &(dyn Any + Send + Sync)) {
+    scene.add_component(id, value.downcast_ref::<T>().unwrap().clone());
+}
+
+fn serialize_component<T: Component + Serialize + 'static>(scene: &Scene, id: EntityId) -> Option<serde_json::Result<Value>> {
+    scene.get_component::<T>(id).map(serde_json::to_value)
+}
+
+fn deserialize_component<T: Component + DeserializeOwned + 'static>(
+    scene: &mut Scene,
+    id: EntityId,
+    value: Value,
+) -> serde_json::Result<()> {
+    scene.add_component(id, serde_json::from_value::<T>(value)?);
+    Ok(())
+}
+
+// Replaces the value of an existing component without running hooks and observers
+fn overwrite_component<T: Component + DeserializeOwned + 'static>(scene: &mut Scene, id: EntityId, value: Value) -> serde_json::Result<()> {
+    let component = serde_json::from_value::<T>(value)?;
+    if let Some(current) = scene.get_component_mut::<T>(id) {
+        *current = component;
+    }
+    Ok(())
+}
+
+fn decode_component<T: Component + DeserializeOwned + 'static>(value: Value) -> serde_json::Result<PendingComponent> {
+    let component = serde_json::from_value::<T>(value)?;
+    Ok(Box::new(move |scene: &mut Scene, id: EntityId| {
+        scene.add_component(id, component);
+    }))
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    Format(serde_json::Error),
+    DuplicateEntity(EntityId),
+    InvalidIndex(EntityId),
+    MissingParent(EntityId),
+    ParentCycle(EntityId),
+    PrefabRootCount(usize),
+    UnknownComponent(String),
+    InvalidOverride(String),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(error) => write!(f, "cannot access scene file: {}", error),
+            SceneError::Format(error) => write!(f, "invalid scene file: {}", error),
+            SceneError::DuplicateEntity(id) => write!(f, "entity {:?} is saved more than once", id),
+            SceneError::InvalidIndex(id) => write!(f, "entity {:?} has an index beyond the saved slots", id),
+            SceneError::MissingParent(id) => write!(f, "parent of entity {:?} is not in the scene file", id),
+            SceneError::ParentCycle(id) => write!(f, "entity {:?} is not below the root, its parents form a cycle", id),
+            SceneError::PrefabRootCount(count) => write!(f, "prefab must have exactly one root entity, found {}", count),
+            SceneError::UnknownComponent(name) => write!(f, "component {} is not registered", name),
+            SceneError::InvalidOverride(reason) => write!(f, "invalid prefab override: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<std::io::Error> for SceneError {
+    fn from(error: std::io::Error) -> SceneError {
+        SceneError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for SceneError {
+    fn from(error: serde_json::Error) -> SceneError {
+        SceneError::Format(error)
+    }
+}
+
+// A component that was left out because its type is not in the registry
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SkippedComponent {
+    pub entity: EntityId,
+    pub type_name: String,
+}
+
+#[derive(Default, Debug)]
+pub struct SerializationReport {
+    skipped: Vec<SkippedComponent>,
+}
+
+impl SerializationReport {
+    pub fn get_skipped(&self) -> &[SkippedComponent] {
+        &self.skipped
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.skipped.is_empty()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedEntity {
+    id: EntityId,
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parent: Option<EntityId>,
+    components: BTreeMap<String, Value>,
+}
+
+// The slot count bounds the entity indices, files without it only allow indices below the number of entities
+#[derive(Serialize, Deserialize)]
+struct SavedScene {
+    #[serde(default)]
+    slot_count: usize,
+    entities: Vec<SavedEntity>,
+}
+
+// The hierarchy is saved as the parent of each entity
+pub(crate) fn is_hierarchy(type_id: TypeId) -> bool {
+    type_id == TypeId::of::<Parent>() || type_id == TypeId::of::<Children>()
+}
+
+pub(crate) fn save(scene: &Scene, path: &Path) -> Result<SerializationReport, SceneError> {
+    let registry = scene.get_component_registry();
+    let mut report = SerializationReport::default();
+    let mut saved = SavedScene { slot_count: scene.get_slot_count(), entities: vec![] };
+    for entity in scene.get_entities() {
+        let id = entity.get_id();
+        let mut components = BTreeMap::new();
+        for (type_id, type_name) in scene.get_component_types(id) {
+            if is_hierarchy(type_id) {
+                continue;
+            }
+            match registry.get_by_type(type_id) {
+                Some(entry) => {
+                    let value = (entry.serialize)(scene, id).unwrap()?;
+                    components.insert(entry.name.clone(), value);
+                }
+                None => report
+                    .skipped
+                    .push(SkippedComponent { entity: id, type_name: String::from(type_name) }),
+            }
+        }
+        saved.entities.push(SavedEntity {
+            id,
+            name: entity.get_name(),
+            parent: scene.get_parent(id),
+            components,
+        });
+    }
+    std::fs::write(path, serde_json::to_string_pretty(&saved)?)?;
+    Ok(report)
+}
+
+// Ids are unique by index, since two generations of one index can not be alive at the same time
+fn read_scene(path: &Path) -> Result<SavedScene, SceneError> {
+    let saved: SavedScene = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    let mut indices = HashSet::new();
+    for entity in saved.entities.iter() {
+        if !indices.insert(entity.id.get_index()) {
+            return Err(SceneError::DuplicateEntity(entity.id));
+        }
+    }
+    let ids: HashSet<EntityId> = saved.entities.iter().map(|x| x.id).collect();
+    for entity in saved.entities.iter() {
+        if entity.parent.is_some_and(|x| !ids.contains(&x)) {
+            return Err(SceneError::MissingParent(entity.id));
+        }
+    }
+    Ok(saved)
+}
+
+// Returns an entity whose parents lead back to itself
+fn find_parent_cycle(entities: &[SavedEntity]) -> Option<EntityId> {
+    let parents: HashMap<EntityId, EntityId> = entities.iter().filter_map(|x| Some((x.id, x.parent?))).collect();
+    let mut acyclic = HashSet::new();
+    for entity in entities.iter() {
+        let mut path = HashSet::new();
+        let mut current = Some(entity.id);
+        while let Some(id) = current {
+            if acyclic.contains(&id) {
+                break;
+            }
+            if !path.insert(id) {
+                return Some(id);
+            }
+            current = parents.get(&id).copied();
+        }
+        acyclic.extend(path);
+    }
+    None
+}
+
+// The whole file is checked and every component deserialized before the entities of the scene are replaced,
+// so a failed load leaves the scene as it was
+pub(crate) fn load(scene: &mut Scene, path: &Path) -> Result<SerializationReport, SceneError> {
+    let mut saved = read_scene(path)?;
+    // Every index below the largest one gets a slot, so an implausible index is rejected before anything is allocated
+    let slot_count = saved.slot_count.max(saved.entities.len());
+    if let Some(entity) = saved.entities.iter().find(|x| x.id.get_index() as usize >= slot_count) {
+        return Err(SceneError::InvalidIndex(entity.id));
+    }
+    if let Some(id) = find_parent_cycle(&saved.entities) {
+        return Err(SceneError::ParentCycle(id));
+    }
+    let registry = scene.get_component_registry();
+    let mut report = SerializationReport::default();
+    let mut pending = vec![];
+    for entity in saved.entities.iter_mut() {
+        let mut components = vec![];
+        for (name, value) in std::mem::take(&mut entity.components) {
+            match registry.get_by_name(&name) {
+                Some(entry) => components.push((entry.decode)(value)?),
+                None => report.skipped.push(SkippedComponent { entity: entity.id, type_name: name }),
+            }
+        }
+        pending.push(components);
+    }
+
+    scene.clear_entities();
+    for entity in saved.entities.iter() {
+        let restored = scene.restore_entity(entity.id, entity.name.clone());
+        debug_assert!(restored, "saved indices are unique");
+    }
+    for (entity, components) in saved.entities.iter().zip(pending) {
+        if let Some(parent) = entity.parent {
+            let linked = scene.set_parent(entity.id, parent);
+            debug_assert!(linked, "saved parents have no cycles");
+        }
+        for insert in components {
+            insert(scene, entity.id);
+        }
+    }
+    Ok(report)
+}
+
+// Adds the component by deserializing a copy of the saved value
+struct SavedComponent {
+    value: Value,
+    deserialize: DeserializeFn,
+}
+
+impl PrefabComponent for SavedComponent {
+    fn insert(&self, scene: &mut Scene, id: EntityId) -> Result<(), SceneError> {
+        (self.deserialize)(scene, id, self.value.clone())?;
+        Ok(())
+    }
+}
+
+// Prefab files use the scene format. The one entity without a parent is the root of the prefab
+// and the saved ids only link children to their parents.
+pub(crate) fn load_prefab(registry: &ComponentRegistry, path: &Path) -> Result<Prefab, SceneError> {
+    let saved = read_scene(path)?;
+    let roots: Vec<&SavedEntity> = saved.entities.iter().filter(|x| x.parent.is_none()).collect();
+    if roots.len() != 1 {
+        return Err(SceneError::PrefabRootCount(roots.len()));
+    }
+    let mut visited = vec![];
+    let prefab = build_prefab(registry, &saved.entities, roots[0], &mut visited)?;
+    match saved.entities.iter().find(|x| !visited.contains(&x.id)) {
+        Some(unreachable) => Err(SceneError::ParentCycle(unreachable.id)),
+        None => Ok(prefab),
+    }
+}
+
+fn build_prefab(
+    registry: &ComponentRegistry,
+    entities: &[SavedEntity],
+    entity: &SavedEntity,
+    visited: &mut Vec<EntityId>,
+) -> Result<Prefab, SceneError> {
+    visited.push(entity.id);
+    let mut prefab = Prefab::new(&entity.name);
+    for (name, value) in entity.components.iter() {
+        let entry = match registry.get_by_name(name) {
+            Some(entry) => entry,
+            None => return Err(SceneError::UnknownComponent(name.clone())),
+        };
+        prefab.push_component(Box::new(SavedComponent { value: value.clone(), deserialize: entry.deserialize }));
+    }
+    for child in entities.iter().filter(|x| x.parent == Some(entity.id)) {
+        prefab = prefab.with_child(build_prefab(registry, entities, child, visited)?);
+    }
+    Ok(prefab)
+}
+
+// Replaces one field of a registered component by saving the component, editing the value and writing it back in place
+pub(crate) fn override_field(scene: &mut Scene, id: EntityId, component: &str, field: &str, value: Value) -> Result<(), SceneError> {
+    let entry = match scene.get_component_registry().get_by_name(component) {
+        Some(entry) => entry.clone(),
+        None => return Err(SceneError::UnknownComponent(String::from(component))),
+    };
+    let mut saved = match (entry.serialize)(scene, id) {
+        Some(saved) => saved?,
+        None => {
+            let reason = format!("entity {:?} has no component {}", id, component);
+            return Err(SceneError::InvalidOverride(reason));
+        }
+    };
+    match saved.as_object_mut().and_then(|x| x.get_mut(field)) {
+        Some(saved_field) => *saved_field = value,
+        None => {
+            let reason = format!("component {} has no field {}", component, field);
+            return Err(SceneError::InvalidOverride(reason));
+        }
+    }
+    (entry.overwrite)(scene, id, saved)?;
+    Ok(())
+}
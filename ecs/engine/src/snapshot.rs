@@ -0,0 +1,121 @@
+use crate::entity::EntityId;
+use crate::hierarchy::Children;
+use crate::hierarchy::Parent;
+use crate::relation::Relations;
+use crate::scene::EntityIds;
+use crate::scene::Scene;
+use crate::serialization;
+use crate::serialization::ComponentCopy;
+use crate::serialization::SkippedComponent;
+
+// One entity with its hierarchy links and the components that could be copied
+pub(crate) struct EntitySnapshot {
+    id: EntityId,
+    name: String,
+    parent: Option<EntityId>,
+    children: Vec<EntityId>,
+    components: Vec<ComponentCopy>,
+}
+
+// The entities of a scene at one point in time with the relations between them. Components that are not registered for
+// cloning or serialization are left out and listed as skipped.
+pub struct SceneSnapshot {
+    ids: EntityIds,
+    entities: Vec<EntitySnapshot>,
+    relations: Relations,
+    skipped: Vec<SkippedComponent>,
+}
+
+impl SceneSnapshot {
+    pub fn get_entity_count(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn get_skipped(&self) -> &[SkippedComponent] {
+        &self.skipped
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.skipped.is_empty()
+    }
+}
+
+impl EntitySnapshot {
+    // Components that can not be copied are added to skipped
+    pub(crate) fn take(scene: &Scene, id: EntityId, skipped: &mut Vec<SkippedComponent>) -> EntitySnapshot {
+        let registry = scene.get_component_registry();
+        let mut components = vec![];
+        for (type_id, type_name) in scene.get_component_types(id) {
+            if serialization::is_hierarchy(type_id) {
+                continue;
+            }
+            match registry.copy_component(scene, id, type_id) {
+                Some(Ok(component)) => components.push(component),
+                _ => skipped.push(SkippedComponent { entity: id, type_name: String::from(type_name) }),
+            }
+        }
+        EntitySnapshot {
+            id,
+            name: scene.get_entity_by_id(id).unwrap().get_name(),
+            parent: scene.get_parent(id),
+            children: scene.get_children(id).to_vec(),
+            components,
+        }
+    }
+
+    pub(crate) fn get_id(&self) -> EntityId {
+        self.id
+    }
+
+    pub(crate) fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    // Adds the hierarchy links and the components to the entity with the id of the snapshot.
+    // The parent keeps its own list of children.
+    pub(crate) fn restore_components(&self, scene: &mut Scene) {
+        if let Some(parent) = self.parent {
+            scene.add_component(self.id, Parent::new(parent));
+        }
+        if !self.children.is_empty() {
+            let mut children = Children::new();
+            for child in self.children.iter() {
+                children.push(*child);
+            }
+            scene.add_component(self.id, children);
+        }
+        for component in self.components.iter() {
+            component
+                .restore(scene, self.id)
+                .expect("snapshot components are checked to deserialize when they are taken");
+        }
+    }
+}
+
+// Entities are kept in the order of the archetype rows so restored queries iterate in the same order
+pub(crate) fn take(scene: &Scene) -> SceneSnapshot {
+    let mut entities = vec![];
+    let mut skipped = vec![];
+    for archetype in scene.get_archetypes() {
+        for id in archetype.get_entities().iter().copied() {
+            entities.push(EntitySnapshot::take(scene, id, &mut skipped));
+        }
+    }
+    SceneSnapshot {
+        ids: scene.get_entity_ids(),
+        entities,
+        relations: scene.get_relations().clone(),
+        skipped,
+    }
+}
+
+// Every entity gets all of its components before the next one is inserted,
+// which puts the entities back into the archetype rows they were taken from
+pub(crate) fn restore(scene: &mut Scene, snapshot: &SceneSnapshot) {
+    scene.reset_entity_ids(&snapshot.ids);
+    for entity in snapshot.entities.iter() {
+        scene.insert_entity(entity.id, entity.name.clone());
+        entity.restore_components(scene);
+    }
+    scene.set_relations(snapshot.relations.clone());
+}
@@ -0,0 +1,131 @@
+use crate::scene::Scene;
+use crate::schedule::Schedule;
+use crate::schedule::Stage;
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+// A value of the app flow such as loading, menu, playing or paused
+pub trait States: Clone + PartialEq + Eq + Hash + Debug + Send + Sync + 'static {}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug + Send + Sync + 'static> States for T {}
+
+// The current state, changed only by the scene when it applies a NextState request
+#[derive(Debug)]
+pub struct State<S: States> {
+    current: S,
+}
+
+impl<S: States> State<S> {
+    pub fn get(&self) -> &S {
+        &self.current
+    }
+}
+
+// A requested transition, applied at the start of the next update
+#[derive(Debug)]
+pub struct NextState<S: States> {
+    next: Option<S>,
+}
+
+impl<S: States> NextState<S> {
+    // Replaces an earlier request of the same frame
+    pub fn set(&mut self, state: S) {
+        self.next = Some(state);
+    }
+
+    pub fn get(&self) -> Option<&S> {
+        self.next.as_ref()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum TransitionKind {
+    Enter,
+    Exit,
+}
+
+// Runs once when the state becomes S, including the initial state on the first update
+pub struct OnEnter<S>(pub S);
+
+// Runs once when the state stops being S
+pub struct OnExit<S>(pub S);
+
+pub trait Transition {
+    type State: States;
+
+    fn into_parts(self) -> (TransitionKind, Self::State);
+}
+
+impl<S: States> Transition for OnEnter<S> {
+    type State = S;
+
+    fn into_parts(self) -> (TransitionKind, S) {
+        (TransitionKind::Enter, self.0)
+    }
+}
+
+impl<S: States> Transition for OnExit<S> {
+    type State = S;
+
+    fn into_parts(self) -> (TransitionKind, S) {
+        (TransitionKind::Exit, self.0)
+    }
+}
+
+pub(crate) trait StateTransitions: Send + Sync {
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn apply(&mut self, scene: &mut Scene);
+}
+
+// The transition schedules of one state type. Their systems run in the Update stage of the schedule.
+pub(crate) struct Transitions<S: States> {
+    schedules: HashMap<(TransitionKind, S), Schedule>,
+    entered: bool,
+}
+
+impl<S: States> Transitions<S> {
+    pub(crate) fn new() -> Transitions<S> {
+        Transitions { schedules: HashMap::new(), entered: false }
+    }
+
+    pub(crate) fn get_schedule_mut(&mut self, kind: TransitionKind, state: S) -> &mut Schedule {
+        self.schedules.entry((kind, state)).or_default()
+    }
+
+    fn run(&mut self, kind: TransitionKind, state: S, scene: &mut Scene) {
+        if let Some(schedule) = self.schedules.get_mut(&(kind, state)) {
+            schedule.run_stage(Stage::Update, scene);
+        }
+    }
+}
+
+impl<S: States> StateTransitions for Transitions<S> {
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    // Requests made by the transition systems themselves are applied on the next update
+    fn apply(&mut self, scene: &mut Scene) {
+        let current = match scene.get_resource::<State<S>>() {
+            Some(state) => state.get().clone(),
+            None => return,
+        };
+        if !self.entered {
+            self.entered = true;
+            self.run(TransitionKind::Enter, current.clone(), scene);
+        }
+        let next = match scene.get_resource_mut::<NextState<S>>().and_then(|x| x.next.take()) {
+            Some(next) if next != current => next,
+            _ => return,
+        };
+        self.run(TransitionKind::Exit, current, scene);
+        scene.get_resource_mut::<State<S>>().unwrap().current = next.clone();
+        self.run(TransitionKind::Enter, next, scene);
+    }
+}
+
+pub(crate) fn create_resources<S: States>(initial: S) -> (State<S>, NextState<S>) {
+    (State { current: initial }, NextState { next: None })
+}
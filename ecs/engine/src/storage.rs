@@ -0,0 +1,304 @@
+use crate::archetype::Archetype;
+use crate::archetype::ComponentTicks;
+use crate::command::Commands;
+use crate::entity::Component;
+use crate::entity::EntityId;
+use crate::entity::UpdateContext;
+use crate::resource::Resources;
+use std::any::type_name;
+use std::any::Any;
+use std::any::TypeId;
+use std::cell::UnsafeCell;
+use std::collections::HashMap;
+
+// Where the components of a type are kept, chosen with Component::STORAGE.
+// Table components are stored in the archetype columns, which makes iterating them fast,
+// but adding or removing one moves all components of the entity to another archetype.
+// Sparse set components are stored per type outside the archetypes, which makes adding and
+// removing them cheap, but queries have to look them up entity by entity.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StorageType {
+    Table,
+    SparseSet,
+}
+
+pub(crate) trait SparseColumn: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn get_type_name(&self) -> &'static str;
+    fn new_empty(&self) -> Box<dyn SparseColumn>;
+    fn contains(&self, id: EntityId) -> bool;
+    // Moves the component of the entity to the same set of another scene, where it counts as added at the tick
+    fn transfer(&mut self, id: EntityId, target: &mut dyn SparseColumn, target_id: EntityId, tick: u64);
+    fn update(&mut self, resources: &Resources, commands: &mut Commands, tick: u64);
+    fn on_entity_destroyed(&mut self, id: EntityId);
+}
+
+// The components are packed densely and found through the index of their entity
+pub(crate) struct SparseSet<T> {
+    dense_indices: Vec<Option<u32>>,
+    entities: Vec<EntityId>,
+    values: Vec<T>,
+    ticks: Vec<ComponentTicks>,
+}
+
+impl<T: Component + 'static> SparseSet<T> {
+    fn new() -> SparseSet<T> {
+        SparseSet {
+            dense_indices: vec![],
+            entities: vec![],
+            values: vec![],
+            ticks: vec![],
+        }
+    }
+
+    fn get_dense_index(&self, id: EntityId) -> Option<usize> {
+        let index = (*self.dense_indices.get(id.get_index() as usize)?)? as usize;
+        if self.entities[index] == id {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    // Returns the replaced component if the entity already had one
+    pub(crate) fn insert(&mut self, id: EntityId, component: T, tick: u64) -> Option<T> {
+        if let Some(index) = self.get_dense_index(id) {
+            self.ticks[index] = ComponentTicks::new(tick);
+            return Some(std::mem::replace(&mut self.values[index], component));
+        }
+        let sparse_index = id.get_index() as usize;
+        if self.dense_indices.len() <= sparse_index {
+            self.dense_indices.resize(sparse_index + 1, None);
+        }
+        self.dense_indices[sparse_index] = Some(self.entities.len() as u32);
+        self.entities.push(id);
+        self.values.push(component);
+        self.ticks.push(ComponentTicks::new(tick));
+        None
+    }
+
+    pub(crate) fn remove(&mut self, id: EntityId) -> Option<(T, ComponentTicks)> {
+        let index = self.get_dense_index(id)?;
+        self.dense_indices[id.get_index() as usize] = None;
+        self.entities.swap_remove(index);
+        if let Some(moved) = self.entities.get(index) {
+            self.dense_indices[moved.get_index() as usize] = Some(index as u32);
+        }
+        Some((self.values.swap_remove(index), self.ticks.swap_remove(index)))
+    }
+
+    pub(crate) fn get(&self, id: EntityId) -> Option<&T> {
+        Some(&self.values[self.get_dense_index(id)?])
+    }
+
+    pub(crate) fn get_ticks(&self, id: EntityId) -> Option<ComponentTicks> {
+        Some(self.ticks[self.get_dense_index(id)?])
+    }
+
+    pub(crate) fn get_mut(&mut self, id: EntityId) -> Option<(&mut T, &mut ComponentTicks)> {
+        let index = self.get_dense_index(id)?;
+        Some((&mut self.values[index], &mut self.ticks[index]))
+    }
+
+    // The caller must hold the access to the component that it uses the returned pointers for.
+    // Only a shared borrow of the set is taken and writes go to the buffers the vectors own.
+    pub(crate) fn get_ptr(&self, id: EntityId) -> Option<(*mut T, *mut ComponentTicks)> {
+        let index = self.get_dense_index(id)?;
+        let values = self.values.as_ptr() as *mut T;
+        let ticks = self.ticks.as_ptr() as *mut ComponentTicks;
+        Some(unsafe { (values.add(index), ticks.add(index)) })
+    }
+}
+
+impl<T: Component + 'static> SparseColumn for SparseSet<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn get_type_name(&self) -> &'static str {
+        type_name::<T>()
+    }
+
+    fn new_empty(&self) -> Box<dyn SparseColumn> {
+        Box::new(SparseSet::<T>::new())
+    }
+
+    fn contains(&self, id: EntityId) -> bool {
+        self.get_dense_index(id).is_some()
+    }
+
+    fn transfer(&mut self, id: EntityId, target: &mut dyn SparseColumn, target_id: EntityId, tick: u64) {
+        let target = target.as_any_mut().downcast_mut::<SparseSet<T>>().unwrap();
+        if let Some((value, _)) = self.remove(id) {
+            target.insert(target_id, value, tick);
+        }
+    }
+
+    fn update(&mut self, resources: &Resources, commands: &mut Commands, tick: u64) {
+        let rows = self.values.iter_mut().zip(self.ticks.iter_mut());
+        for ((component, ticks), entity) in rows.zip(self.entities.iter()) {
+            let mut context = UpdateContext::new(*entity, resources, commands);
+            component.update(&mut context);
+            if context.is_changed() {
+                ticks.set_changed(tick);
+            }
+        }
+    }
+
+    fn on_entity_destroyed(&mut self, id: EntityId) {
+        if let Some((mut component, _)) = self.remove(id) {
+            component.on_entity_destroyed(id);
+            component.on_remove(id);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SparseSets {
+    // Kept in the order the types were first added so updates run in a stable order.
+    // Queries hand out mutable access to one set while other systems use other sets.
+    sets: Vec<UnsafeCell<Box<dyn SparseColumn>>>,
+    indices: HashMap<TypeId, usize>,
+}
+
+// A set is only written through a shared reference by systems whose declared
+// access the schedule has checked not to conflict with any concurrently running system.
+unsafe impl Sync for SparseSets {}
+
+impl SparseSets {
+    pub(crate) fn get<T: Component + 'static>(&self) -> Option<&SparseSet<T>> {
+        let index = *self.indices.get(&TypeId::of::<T>())?;
+        let set = unsafe { &*self.sets[index].get() };
+        Some(set.as_any().downcast_ref::<SparseSet<T>>().unwrap())
+    }
+
+    pub(crate) fn get_mut<T: Component + 'static>(&mut self) -> Option<&mut SparseSet<T>> {
+        let index = *self.indices.get(&TypeId::of::<T>())?;
+        let set = self.sets[index].get_mut();
+        Some(set.as_any_mut().downcast_mut::<SparseSet<T>>().unwrap())
+    }
+
+    pub(crate) fn get_or_insert<T: Component + 'static>(&mut self) -> &mut SparseSet<T> {
+        let index = self.get_or_insert_index(TypeId::of::<T>(), || Box::new(SparseSet::<T>::new()));
+        let set = self.sets[index].get_mut();
+        set.as_any_mut().downcast_mut::<SparseSet<T>>().unwrap()
+    }
+
+    fn get_or_insert_index<F: FnOnce() -> Box<dyn SparseColumn>>(&mut self, type_id: TypeId, create: F) -> usize {
+        if let Some(index) = self.indices.get(&type_id) {
+            return *index;
+        }
+        self.sets.push(UnsafeCell::new(create()));
+        self.indices.insert(type_id, self.sets.len() - 1);
+        self.sets.len() - 1
+    }
+
+    // Returns the type ids and type names of the sets that hold a component of the entity
+    pub(crate) fn get_component_types(&self, id: EntityId) -> Vec<(TypeId, &'static str)> {
+        let mut types: Vec<(TypeId, &'static str)> = self
+            .indices
+            .iter()
+            .map(|(type_id, index)| (*type_id, unsafe { &*self.sets[*index].get() }))
+            .filter(|(_, set)| set.contains(id))
+            .map(|(type_id, set)| (type_id, set.get_type_name()))
+            .collect();
+        types.sort_by_key(|(type_id, _)| *type_id);
+        types
+    }
+
+    pub(crate) fn update(&mut self, resources: &Resources, commands: &mut Commands, tick: u64) {
+        for set in self.sets.iter_mut() {
+            set.get_mut().update(resources, commands, tick);
+        }
+    }
+
+    // Runs the destroy hooks of the entity's components and drops them
+    pub(crate) fn on_entity_destroyed(&mut self, id: EntityId) {
+        for set in self.sets.iter_mut() {
+            set.get_mut().on_entity_destroyed(id);
+        }
+    }
+
+    pub(crate) fn transfer(&mut self, id: EntityId, target: &mut SparseSets, target_id: EntityId, tick: u64) {
+        for (type_id, index) in self.indices.iter() {
+            let set = self.sets[*index].get_mut();
+            if !set.contains(id) {
+                continue;
+            }
+            let target_index = target.get_or_insert_index(*type_id, || set.new_empty());
+            set.transfer(id, target.sets[target_index].get_mut().as_mut(), target_id, tick);
+        }
+    }
+}
+
+// Where a query finds the components of type T for the rows of one archetype.
+// Branching on T::STORAGE instead of the fields lets table queries compile to plain column reads.
+pub struct ColumnState<T> {
+    values: *mut T,
+    ticks: *mut ComponentTicks,
+    entities: *const EntityId,
+    set: *const SparseSet<T>,
+}
+
+impl<T: Component + 'static> ColumnState<T> {
+    // The caller must hold the access to T that the query reports
+    pub(crate) unsafe fn new(archetype: &Archetype, sparse_sets: &SparseSets) -> ColumnState<T> {
+        let mut state = ColumnState {
+            values: std::ptr::null_mut(),
+            ticks: std::ptr::null_mut(),
+            entities: archetype.get_entities().as_ptr(),
+            set: std::ptr::null(),
+        };
+        match T::STORAGE {
+            StorageType::Table => {
+                if let Some((values, ticks)) = archetype.get_column_ptr::<T>() {
+                    state.values = values;
+                    state.ticks = ticks;
+                }
+            }
+            StorageType::SparseSet => {
+                if let Some(set) = sparse_sets.get::<T>() {
+                    state.set = set;
+                }
+            }
+        }
+        state
+    }
+
+    // The row must be in bounds of the archetype the state came from
+    pub(crate) unsafe fn get(&self, row: usize) -> Option<(*mut T, *mut ComponentTicks)> {
+        match T::STORAGE {
+            StorageType::Table if self.values.is_null() => None,
+            StorageType::Table => Some((self.values.add(row), self.ticks.add(row))),
+            StorageType::SparseSet if self.set.is_null() => None,
+            StorageType::SparseSet => (*self.set).get_ptr(*self.entities.add(row)),
+        }
+    }
+
+    // Table columns exist for every row of an archetype that matched.
+    // Sparse sets are only asked whether they hold the entity, no component is touched.
+    pub(crate) unsafe fn has(&self, row: usize) -> bool {
+        match T::STORAGE {
+            StorageType::Table => true,
+            StorageType::SparseSet => !self.set.is_null() && (*self.set).contains(*self.entities.add(row)),
+        }
+    }
+
+    // The row must be in bounds and have the component
+    pub(crate) unsafe fn get_unchecked(&self, row: usize) -> (*mut T, *mut ComponentTicks) {
+        match T::STORAGE {
+            StorageType::Table => (self.values.add(row), self.ticks.add(row)),
+            StorageType::SparseSet => (*self.set).get_ptr(*self.entities.add(row)).unwrap(),
+        }
+    }
+}
+
+// Whether rows of the archetype can have a component of type T
+pub(crate) fn may_have<T: Component + 'static>(archetype: &Archetype) -> bool {
+    T::STORAGE == StorageType::SparseSet || archetype.has_component(TypeId::of::<T>())
+}
@@ -0,0 +1,171 @@
+use crate::command::Commands;
+use crate::entity::Component;
+use crate::entity::EntityId;
+use crate::event::EventWriter;
+use crate::event::Events;
+use crate::query::Access;
+use crate::query::Fetch;
+use crate::query::QueryFilter;
+use crate::query::QueryIter;
+use crate::query::RemovedComponents;
+use crate::query::Ticks;
+use crate::relation::Relation;
+use crate::scene::Scene;
+
+pub trait System: Send + Sync {
+    fn get_name(&self) -> &str;
+    fn get_access(&self) -> Access;
+    fn run(&mut self, context: &mut SystemContext);
+}
+
+pub struct FunctionSystem<F> {
+    name: String,
+    access: Access,
+    function: F,
+}
+
+impl<F: FnMut(&mut SystemContext) + Send + Sync> FunctionSystem<F> {
+    pub fn new(name: &str, function: F) -> FunctionSystem<F> {
+        FunctionSystem { name: String::from(name), access: Access::new(), function }
+    }
+
+    pub fn reads<T: Component + 'static>(mut self) -> FunctionSystem<F> {
+        self.access.add_read::<T>();
+        self
+    }
+
+    pub fn writes<T: Component + 'static>(mut self) -> FunctionSystem<F> {
+        self.access.add_write::<T>();
+        self
+    }
+
+    pub fn reads_resource<R: Send + Sync + 'static>(mut self) -> FunctionSystem<F> {
+        self.access.add_read::<R>();
+        self
+    }
+
+    pub fn writes_resource<R: Send + Sync + 'static>(mut self) -> FunctionSystem<F> {
+        self.access.add_write::<R>();
+        self
+    }
+
+    pub fn reads_events<E: Send + Sync + 'static>(self) -> FunctionSystem<F> {
+        self.reads_resource::<Events<E>>()
+    }
+
+    pub fn writes_events<E: Send + Sync + 'static>(self) -> FunctionSystem<F> {
+        self.writes_resource::<Events<E>>()
+    }
+}
+
+impl<F: FnMut(&mut SystemContext) + Send + Sync> System for FunctionSystem<F> {
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_access(&self) -> Access {
+        self.access.clone()
+    }
+
+    fn run(&mut self, context: &mut SystemContext) {
+        (self.function)(context);
+    }
+}
+
+// Gives a running system access to the scene, limited to what the system declared.
+// Systems may run in parallel so the scene is shared and the declared access is what
+// the schedule uses to keep conflicting systems apart.
+pub struct SystemContext<'a> {
+    scene: &'a Scene,
+    name: &'a str,
+    access: &'a Access,
+    commands: &'a mut Commands,
+    ticks: Ticks,
+}
+
+impl<'a> SystemContext<'a> {
+    pub(crate) fn new(scene: &'a Scene, name: &'a str, access: &'a Access, commands: &'a mut Commands, ticks: Ticks) -> SystemContext<'a> {
+        SystemContext { scene, name, access, commands, ticks }
+    }
+
+    pub fn get_name(&self) -> &str {
+        self.name
+    }
+
+    // Change detection inside the system is relative to the last run of the system
+    pub fn get_ticks(&self) -> Ticks {
+        self.ticks
+    }
+
+    pub fn query<Q: Fetch>(&mut self) -> QueryIter<'_, Q> {
+        self.query_filtered::<Q, ()>()
+    }
+
+    pub fn query_filtered<Q: Fetch, F: QueryFilter>(&mut self) -> QueryIter<'_, Q, F> {
+        let mut access = Access::new();
+        Q::add_access(&mut access);
+        F::add_access(&mut access);
+        self.check_access(&access);
+        unsafe { self.scene.query_unchecked::<Q, F>(self.ticks) }
+    }
+
+    pub fn get_component<T: Component + 'static>(&self, id: EntityId) -> Option<&T> {
+        let mut access = Access::new();
+        access.add_read::<T>();
+        self.check_access(&access);
+        self.scene.get_component::<T>(id)
+    }
+
+    pub fn get_component_mut<T: Component + 'static>(&mut self, id: EntityId) -> Option<&mut T> {
+        let mut access = Access::new();
+        access.add_write::<T>();
+        self.check_access(&access);
+        unsafe { self.scene.get_component_unchecked_mut::<T>(id, self.ticks.get_this_run()) }
+    }
+
+    pub fn get_removed_components<T: Component + 'static>(&self) -> RemovedComponents<'_, T> {
+        self.scene.get_removed_components_since::<T>(self.ticks.get_last_run())
+    }
+
+    // Relations only change when commands are applied, so reading them needs no declared access
+    pub fn get_relation_targets<R: Relation>(&self, source: EntityId) -> &[EntityId] {
+        self.scene.get_relation_targets::<R>(source)
+    }
+
+    pub fn get_relation_sources<R: Relation>(&self, target: EntityId) -> &[EntityId] {
+        self.scene.get_relation_sources::<R>(target)
+    }
+
+    pub fn get_resource<R: Send + Sync + 'static>(&self) -> Option<&R> {
+        let mut access = Access::new();
+        access.add_read::<R>();
+        self.check_access(&access);
+        self.scene.get_resource::<R>()
+    }
+
+    pub fn get_resource_mut<R: Send + Sync + 'static>(&mut self) -> Option<&mut R> {
+        let mut access = Access::new();
+        access.add_write::<R>();
+        self.check_access(&access);
+        unsafe { self.scene.get_resources().get_unchecked_mut::<R>() }
+    }
+
+    pub fn get_events<E: Send + Sync + 'static>(&self) -> Option<&Events<E>> {
+        self.get_resource::<Events<E>>()
+    }
+
+    pub fn get_event_writer<E: Send + Sync + 'static>(&mut self) -> Option<EventWriter<'_, E>> {
+        self.get_resource_mut::<Events<E>>().map(EventWriter::new)
+    }
+
+    // The commands are applied at the end of the stage, after the commands of the systems ordered before this one
+    pub fn get_commands(&mut self) -> &mut Commands {
+        self.commands
+    }
+
+    fn check_access(&self, access: &Access) {
+        if let Some(undeclared) = access.find_undeclared(self.access) {
+            panic!("system {} accesses {} without declaring it", self.name, undeclared);
+        }
+    }
+}
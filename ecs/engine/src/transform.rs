@@ -0,0 +1,188 @@
+use crate::entity::Component;
+use crate::entity::EntityId;
+use crate::query::With;
+use crate::query::Without;
+use crate::scene::Scene;
+use cgmath::Matrix4;
+use cgmath::One;
+use cgmath::Quaternion;
+use cgmath::SquareMatrix;
+use cgmath::Vector3;
+use std::collections::HashSet;
+
+// Position of an entity relative to its parent
+#[derive(Clone, Copy, Debug)]
+pub struct Transform {
+    translation: Vector3<f32>,
+    rotation: Quaternion<f32>,
+    scale: Vector3<f32>,
+    changed: bool,
+}
+
+impl Default for Transform {
+    fn default() -> Transform {
+        Transform::new()
+    }
+}
+
+impl Transform {
+    pub fn new() -> Transform {
+        Transform {
+            translation: Vector3::new(0.0, 0.0, 0.0),
+            rotation: Quaternion::one(),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+            changed: true,
+        }
+    }
+
+    pub fn from_translation(translation: Vector3<f32>) -> Transform {
+        let mut transform = Transform::new();
+        transform.translation = translation;
+        transform
+    }
+
+    pub fn get_translation(&self) -> Vector3<f32> {
+        self.translation
+    }
+
+    pub fn set_translation(&mut self, translation: Vector3<f32>) {
+        self.translation = translation;
+        self.changed = true;
+    }
+
+    pub fn get_rotation(&self) -> Quaternion<f32> {
+        self.rotation
+    }
+
+    pub fn set_rotation(&mut self, rotation: Quaternion<f32>) {
+        self.rotation = rotation;
+        self.changed = true;
+    }
+
+    pub fn get_scale(&self) -> Vector3<f32> {
+        self.scale
+    }
+
+    pub fn set_scale(&mut self, scale: Vector3<f32>) {
+        self.scale = scale;
+        self.changed = true;
+    }
+
+    // Returns true if the transform changed since the last propagation
+    pub fn is_changed(&self) -> bool {
+        self.changed
+    }
+
+    pub(crate) fn mark_changed(&mut self) {
+        self.changed = true;
+    }
+
+    pub fn get_matrix(&self) -> Matrix4<f32> {
+        let scale = Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z);
+        Matrix4::from_translation(self.translation) * Matrix4::from(self.rotation) * scale
+    }
+}
+
+impl Component for Transform {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+// World matrix of an entity, written by the transform propagation pass of Scene::update
+#[derive(Clone, Copy, Debug)]
+pub struct GlobalTransform {
+    matrix: Matrix4<f32>,
+}
+
+impl Default for GlobalTransform {
+    fn default() -> GlobalTransform {
+        GlobalTransform::new()
+    }
+}
+
+impl GlobalTransform {
+    pub fn new() -> GlobalTransform {
+        GlobalTransform { matrix: Matrix4::identity() }
+    }
+
+    pub fn get_matrix(&self) -> Matrix4<f32> {
+        self.matrix
+    }
+
+    pub fn get_translation(&self) -> Vector3<f32> {
+        self.matrix.w.truncate()
+    }
+}
+
+impl Component for GlobalTransform {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+// Computes the world matrices top-down starting at the topmost entities whose transform changed.
+// Subtrees without a changed transform are never visited.
+// Returns the number of global transforms that were written.
+pub(crate) fn propagate_transforms(scene: &mut Scene) -> usize {
+    let missing: Vec<EntityId> = scene
+        .query_filtered::<EntityId, (With<Transform>, Without<GlobalTransform>)>()
+        .collect();
+    for id in missing {
+        scene.add_component(id, GlobalTransform::new());
+        scene.get_component_mut::<Transform>(id).unwrap().mark_changed();
+    }
+
+    let changed: HashSet<EntityId> = scene
+        .query::<(EntityId, &Transform)>()
+        .filter(|(_, transform)| transform.changed)
+        .map(|(id, _)| id)
+        .collect();
+    let mut stack = vec![];
+    for id in changed.iter().copied() {
+        if !has_changed_ancestor(scene, id, &changed) {
+            stack.push((id, get_parent_matrix(scene, id)));
+        }
+    }
+
+    // Every entity below a changed transform is written
+    let mut written = 0;
+    while let Some((id, parent_matrix)) = stack.pop() {
+        let mut matrix = parent_matrix;
+        if let Some(transform) = scene.get_component_mut::<Transform>(id) {
+            transform.changed = false;
+            let local = transform.get_matrix();
+            let global = scene.get_component_mut::<GlobalTransform>(id).unwrap();
+            global.matrix = parent_matrix * local;
+            matrix = global.matrix;
+            written += 1;
+        }
+        for child in scene.get_children(id).iter().rev() {
+            stack.push((*child, matrix));
+        }
+    }
+    written
+}
+
+fn has_changed_ancestor(scene: &Scene, id: EntityId, changed: &HashSet<EntityId>) -> bool {
+    let mut ancestor = scene.get_parent(id);
+    while let Some(id) = ancestor {
+        if changed.contains(&id) {
+            return true;
+        }
+        ancestor = scene.get_parent(id);
+    }
+    false
+}
+
+// The world matrix of the nearest ancestor with a transform, which is up to date when no ancestor changed
+fn get_parent_matrix(scene: &Scene, id: EntityId) -> Matrix4<f32> {
+    let mut ancestor = scene.get_parent(id);
+    while let Some(id) = ancestor {
+        if let Some(global) = scene.get_component::<GlobalTransform>(id) {
+            return global.matrix;
+        }
+        ancestor = scene.get_parent(id);
+    }
+    Matrix4::identity()
+}
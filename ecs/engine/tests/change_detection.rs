@@ -0,0 +1,162 @@
+use engine::entity::Component;
+use engine::entity::EntityId;
+use engine::entity::UpdateContext;
+use engine::query::Added;
+use engine::query::Changed;
+use engine::scene::Scene;
+use engine::schedule::Stage;
+use engine::system::FunctionSystem;
+use engine::system::SystemContext;
+
+struct Position {
+    x: f32,
+}
+
+impl Component for Position {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+// Moves every few updates and reports it through change detection
+struct Pulse {
+    updates: u32,
+}
+
+impl Component for Pulse {
+    fn update(&mut self, context: &mut UpdateContext) {
+        self.updates += 1;
+        if self.updates.is_multiple_of(2) {
+            context.set_changed();
+        }
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[derive(Default)]
+struct Seen {
+    added: Vec<EntityId>,
+    changed: Vec<EntityId>,
+    removed: Vec<EntityId>,
+}
+
+fn take_seen(scene: &mut Scene) -> Seen {
+    std::mem::take(scene.get_resource_mut::<Seen>().unwrap())
+}
+
+fn create_tracking_scene() -> Scene {
+    let mut scene = Scene::new();
+    scene.insert_resource(Seen::default());
+    let tracker = FunctionSystem::new("tracker", |context: &mut SystemContext| {
+        let added: Vec<EntityId> = context.query_filtered::<EntityId, Added<Position>>().collect();
+        let changed: Vec<EntityId> = context.query_filtered::<EntityId, Changed<Position>>().collect();
+        let removed: Vec<EntityId> = context.get_removed_components::<Position>().collect();
+        let seen = context.get_resource_mut::<Seen>().unwrap();
+        seen.added.extend(added);
+        seen.changed.extend(changed);
+        seen.removed.extend(removed);
+    })
+    .reads::<Position>()
+    .writes_resource::<Seen>();
+    scene.add_system(Stage::PostUpdate, tracker);
+    scene
+}
+
+#[test]
+fn systems_see_changes_since_their_last_run() {
+    let mut scene = create_tracking_scene();
+    let moved = scene.create_entity();
+    let idle = scene.create_entity();
+    scene.add_component(moved, Position { x: 0.0 });
+    scene.add_component(idle, Position { x: 0.0 });
+
+    scene.update();
+    let seen = take_seen(&mut scene);
+    assert_eq!(vec![moved, idle], seen.added);
+    assert_eq!(vec![moved, idle], seen.changed);
+
+    scene.update();
+    let seen = take_seen(&mut scene);
+    assert!(seen.added.is_empty());
+    assert!(seen.changed.is_empty());
+
+    scene.get_component_mut::<Position>(moved).unwrap().x = 1.0;
+    scene.update();
+    let seen = take_seen(&mut scene);
+    assert!(seen.added.is_empty());
+    assert_eq!(vec![moved], seen.changed);
+}
+
+#[test]
+fn mutable_queries_mark_changes() {
+    let mut scene = create_tracking_scene();
+    let fast = scene.create_entity();
+    let slow = scene.create_entity();
+    scene.add_component(fast, Position { x: 0.0 });
+    scene.add_component(slow, Position { x: 0.0 });
+    let mover = FunctionSystem::new("mover", move |context: &mut SystemContext| {
+        for (id, position) in context.query::<(EntityId, &mut Position)>() {
+            if id == fast {
+                position.x += 1.0;
+            }
+        }
+    })
+    .writes::<Position>();
+    scene.add_system(Stage::Update, mover);
+    scene.update();
+    take_seen(&mut scene);
+
+    scene.update();
+
+    // Every component the query handed out mutably counts as changed
+    assert_eq!(vec![fast, slow], take_seen(&mut scene).changed);
+    let ticks = scene.get_component_ticks::<Position>(fast).unwrap();
+    assert!(ticks.is_changed(scene.get_last_update_tick()));
+    assert!(!ticks.is_added(scene.get_last_update_tick()));
+}
+
+#[test]
+fn removed_components_are_reported_once() {
+    let mut scene = create_tracking_scene();
+    let taken = scene.create_entity();
+    let destroyed = scene.create_entity();
+    scene.add_component(taken, Position { x: 0.0 });
+    scene.add_component(destroyed, Position { x: 0.0 });
+    scene.update();
+
+    scene.remove_component::<Position>(taken);
+    scene.destroy_entity(destroyed);
+    assert_eq!(
+        vec![taken, destroyed],
+        scene.get_removed_components::<Position>().collect::<Vec<_>>()
+    );
+    scene.update();
+    assert_eq!(vec![taken, destroyed], take_seen(&mut scene).removed);
+
+    scene.update();
+    assert!(take_seen(&mut scene).removed.is_empty());
+    scene.update();
+    assert_eq!(0, scene.get_removed_components::<Position>().count());
+}
+
+#[test]
+fn scene_queries_compare_against_last_update() {
+    let mut scene = Scene::new();
+    let pulse = scene.create_entity();
+    scene.add_component(pulse, Pulse { updates: 0 });
+    let changed = |scene: &mut Scene| scene.query_filtered::<EntityId, Changed<Pulse>>().count();
+    assert_eq!(1, changed(&mut scene));
+
+    scene.update();
+    assert_eq!(0, changed(&mut scene));
+    scene.update();
+    assert_eq!(1, changed(&mut scene));
+    scene.update();
+    assert_eq!(0, changed(&mut scene));
+
+    scene.get_component_mut::<Pulse>(pulse).unwrap();
+    assert_eq!(1, changed(&mut scene));
+}
@@ -0,0 +1,137 @@
+use engine::command::Commands;
+use engine::count_component::CountComponent;
+use engine::entity::Component;
+use engine::entity::EntityId;
+use engine::entity::UpdateContext;
+use engine::scene::Scene;
+use engine::schedule::Stage;
+use engine::system::FunctionSystem;
+use engine::system::SystemContext;
+
+struct Spawner {
+    remaining: u32,
+}
+
+impl Component for Spawner {
+    fn update(&mut self, context: &mut UpdateContext) {
+        if self.remaining == 0 {
+            return;
+        }
+        self.remaining -= 1;
+        let parent = context.get_entity();
+        let name = format!("child of {} #{}", parent.get_index(), self.remaining);
+        context
+            .get_commands()
+            .spawn_with_name(name)
+            .add_component(CountComponent::new())
+            .set_parent(parent);
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+fn get_snapshot(scene: &Scene) -> Vec<(EntityId, String, Option<EntityId>, Option<u64>)> {
+    scene
+        .get_entities()
+        .map(|x| {
+            let id = x.get_id();
+            let count = scene.get_component::<CountComponent>(id).map(|x| x.get_count());
+            (id, x.get_name(), scene.get_parent(id), count)
+        })
+        .collect()
+}
+
+fn run_spawners() -> Scene {
+    let mut scene = Scene::new();
+    for remaining in [2, 3] {
+        let entity = scene.create_entity();
+        scene.add_component(entity, Spawner { remaining });
+    }
+    for _ in 0..4 {
+        scene.update();
+    }
+    scene
+}
+
+#[test]
+fn components_spawn_children_during_update() {
+    let scene = run_spawners();
+
+    let snapshot = get_snapshot(&scene);
+    assert_eq!(7, snapshot.len());
+    let spawner = snapshot[0].0;
+    assert_eq!(2, scene.get_children(spawner).len());
+    let first_child = scene.get_children(spawner)[0];
+    assert_eq!("child of 0 #1", scene.get_entity_by_id(first_child).unwrap().get_name());
+    // Spawned in the first update and counted by the three updates after it
+    assert_eq!(3, scene.get_component::<CountComponent>(first_child).unwrap().get_count());
+    assert_eq!(snapshot, get_snapshot(&run_spawners()));
+}
+
+#[test]
+fn system_commands_apply_in_system_order() {
+    let mut scene = Scene::new();
+    let target = scene.create_entity();
+    scene.add_component(target, CountComponent::new());
+    let despawn = FunctionSystem::new("despawn", move |context: &mut SystemContext| {
+        context.get_commands().despawn(target);
+    });
+    let modify = FunctionSystem::new("modify", move |context: &mut SystemContext| {
+        let count = context.get_component::<CountComponent>(target).unwrap().get_count();
+        context
+            .get_commands()
+            .spawn_with_name(format!("saw {}", count))
+            .add_component(CountComponent::new());
+        context.get_commands().entity(target).remove_component::<CountComponent>();
+    })
+    .reads::<CountComponent>();
+    scene.add_system(Stage::Update, despawn).after("modify");
+    scene.add_system(Stage::Update, modify);
+
+    scene.update();
+
+    assert!(!scene.contains_entity(target));
+    let names: Vec<String> = scene.get_entities().map(|x| x.get_name()).collect();
+    assert_eq!(vec!["saw 1"], names);
+}
+
+#[test]
+fn commands_refer_to_spawned_entities() {
+    let mut scene = Scene::new();
+    let mut commands = Commands::new();
+    let parent = commands.spawn_with_name(String::from("parent")).get_target();
+    commands.spawn_with_name(String::from("child")).set_parent(parent);
+    let doomed = commands.spawn().get_target();
+    commands.despawn(doomed);
+    assert_eq!(5, commands.len());
+
+    scene.apply_commands(&mut commands);
+
+    assert!(commands.is_empty());
+    let parent = scene.get_entity_by_name("parent").unwrap();
+    let child = scene.get_entity_by_name("child").unwrap();
+    assert_eq!(Some(parent), scene.get_parent(child));
+    assert_eq!(2, scene.get_entity_count());
+}
+
+#[test]
+fn spawned_handles_only_resolve_in_their_own_queue() {
+    let mut scene = Scene::new();
+    let mut first = Commands::new();
+    let mut second = Commands::new();
+    let spawned = first.spawn_with_name(String::from("first")).get_target();
+    second.spawn_with_name(String::from("second"));
+    second.entity(spawned).add_component(CountComponent::new());
+    scene.apply_commands(&mut second);
+    assert_eq!(0, scene.query::<&CountComponent>().count());
+
+    // After the queue is applied its old handles do not refer to the entities it spawns next
+    scene.apply_commands(&mut first);
+    first.spawn_with_name(String::from("third"));
+    first.entity(spawned).add_component(CountComponent::new());
+    scene.apply_commands(&mut first);
+    assert_eq!(0, scene.query::<&CountComponent>().count());
+    assert_eq!(3, scene.get_entity_count());
+}
@@ -1,25 +1,153 @@
+use engine::count_component::CountComponent;
+use engine::entity::Component;
+use engine::entity::EntityId;
+use engine::scene::Scene;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+struct NameTag {
+    tag: String,
+}
+
+impl Component for NameTag {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+struct GpuBuffer {
+    handle: u32,
+    log: Arc<Mutex<Vec<String>>>,
+}
+
+impl GpuBuffer {
+    fn record(&self, event: &str, entity: EntityId) {
+        self.log
+            .lock()
+            .unwrap()
+            .push(format!("{} {} {}", event, self.handle, entity.get_index()));
+    }
+}
+
+impl Component for GpuBuffer {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn on_add(&mut self, entity: EntityId) {
+        self.record("add", entity);
+    }
+
+    fn on_remove(&mut self, entity: EntityId) {
+        self.record("remove", entity);
+    }
+
+    fn on_entity_destroyed(&mut self, entity: EntityId) {
+        self.record("destroyed", entity);
+    }
+}
+
+fn take_log(log: &Arc<Mutex<Vec<String>>>) -> Vec<String> {
+    std::mem::take(&mut *log.lock().unwrap())
+}
+
 #[test]
 fn add_count_component() {
-    let mut scene = engine::scene::Scene::new();
+    let mut scene = Scene::new();
     let entity = scene.create_entity();
-    
-    entity
-        .borrow_mut()
-        .add_component(engine::count_component::CountComponent::new());
+
+    scene.add_component(entity, CountComponent::new());
 
     scene.update();
     scene.update();
     scene.update();
 
-    let comp = entity
-        .borrow_mut()
-        .get_component::<engine::count_component::CountComponent>()
-        .unwrap();
-
-    let comp_borrow = comp.borrow();
-    let count_comp = comp_borrow
-        .as_any()
-        .downcast_ref::<engine::count_component::CountComponent>()
-        .unwrap();
+    let count_comp = scene.get_component::<CountComponent>(entity).unwrap();
     assert_eq!(count_comp.get_count(), 3);
 }
+
+#[test]
+fn move_between_archetypes() {
+    let mut scene = Scene::new();
+    let dog = scene.create_entity_with_name(String::from("dog"));
+    let cat = scene.create_entity_with_name(String::from("cat"));
+
+    scene.add_component(dog, CountComponent::new());
+    scene.add_component(cat, CountComponent::new());
+    scene.get_component_mut::<CountComponent>(dog).unwrap().override_count(5);
+    scene.get_component_mut::<CountComponent>(cat).unwrap().override_count(9);
+    scene.add_component(dog, NameTag { tag: String::from("good boy") });
+
+    assert!(scene.has_component::<NameTag>(dog));
+    assert!(!scene.has_component::<NameTag>(cat));
+    assert_eq!(5, scene.get_component::<CountComponent>(dog).unwrap().get_count());
+    assert_eq!(9, scene.get_component::<CountComponent>(cat).unwrap().get_count());
+    assert_eq!("good boy", scene.get_component::<NameTag>(dog).unwrap().tag);
+
+    assert!(scene.remove_component::<CountComponent>(dog));
+    assert!(!scene.remove_component::<CountComponent>(dog));
+    assert!(scene.get_component::<CountComponent>(dog).is_none());
+    assert_eq!("good boy", scene.get_component::<NameTag>(dog).unwrap().tag);
+    assert_eq!(9, scene.get_component::<CountComponent>(cat).unwrap().get_count());
+}
+
+#[test]
+fn share_archetype_tables() {
+    let mut scene = Scene::new();
+    let entities: Vec<_> = (0..100).map(|_| scene.create_entity()).collect();
+    for (i, entity) in entities.iter().enumerate() {
+        scene.add_component(*entity, CountComponent::new());
+        scene.get_component_mut::<CountComponent>(*entity).unwrap().override_count(i as u64);
+    }
+    for entity in entities.iter().step_by(2) {
+        scene.destroy_entity(*entity);
+    }
+
+    let archetype = scene.get_archetypes().iter().find(|x| !x.is_empty()).unwrap();
+    assert_eq!(50, archetype.len());
+    assert_eq!(50, archetype.get_column::<CountComponent>().unwrap().len());
+    for (i, entity) in entities.iter().enumerate().skip(1).step_by(2) {
+        assert_eq!(i as u64, scene.get_component::<CountComponent>(*entity).unwrap().get_count());
+    }
+}
+
+#[test]
+fn take_component() {
+    let mut scene = Scene::new();
+    let entity = scene.create_entity();
+    scene.add_component(entity, CountComponent::new());
+    scene.add_component(entity, NameTag { tag: String::from("cat") });
+    scene.get_component_mut::<CountComponent>(entity).unwrap().override_count(4);
+
+    let taken = scene.take_component::<CountComponent>(entity).unwrap();
+    assert_eq!(4, taken.get_count());
+    assert!(scene.take_component::<CountComponent>(entity).is_none());
+
+    let mut entity_mut = scene.get_entity_mut_by_id(entity).unwrap();
+    assert_eq!("cat", entity_mut.take_component::<NameTag>().unwrap().tag);
+    assert!(!entity_mut.has::<NameTag>());
+}
+
+#[test]
+fn lifecycle_hooks() {
+    let log = Arc::new(Mutex::new(vec![]));
+    let buffer = |handle: u32| GpuBuffer { handle, log: log.clone() };
+    let mut scene = Scene::new();
+    let entity = scene.create_entity();
+
+    scene.add_component(entity, buffer(1));
+    assert_eq!(vec!["add 1 0"], take_log(&log));
+
+    scene.add_component(entity, buffer(2));
+    assert_eq!(vec!["remove 1 0", "add 2 0"], take_log(&log));
+
+    let taken = scene.take_component::<GpuBuffer>(entity).unwrap();
+    assert_eq!(2, taken.handle);
+    assert_eq!(vec!["remove 2 0"], take_log(&log));
+
+    scene.add_component(entity, buffer(3));
+    scene.add_component(entity, CountComponent::new());
+    take_log(&log);
+    scene.destroy_entity(entity);
+    assert_eq!(vec!["destroyed 3 0", "remove 3 0"], take_log(&log));
+}
@@ -0,0 +1,109 @@
+// These tests exercise the aliasing rules of the entity accessors and are meant
+// to also pass under Miri: cargo +nightly miri test -p engine --test entity_ref
+use engine::count_component::CountComponent;
+use engine::entity::Component;
+use engine::scene::Scene;
+
+struct Health {
+    points: i32,
+}
+
+impl Component for Health {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[test]
+fn get_checks_component_type() {
+    let mut scene = Scene::new();
+    let entity = scene.create_entity();
+    scene.add_component(entity, CountComponent::new());
+
+    let entity_mut = scene.get_entity_mut_by_id(entity).unwrap();
+    assert!(entity_mut.get::<CountComponent>().is_some());
+    assert!(entity_mut.get::<Health>().is_none());
+    assert!(entity_mut.get_mut::<Health>().is_none());
+
+    let entity_ref = scene.get_entity_ref_by_id(entity).unwrap();
+    assert!(entity_ref.has::<CountComponent>());
+    assert!(entity_ref.get::<Health>().is_none());
+}
+
+#[test]
+fn get_mut_several_components() {
+    let mut scene = Scene::new();
+    let entity = scene.create_entity();
+    scene.add_component(entity, CountComponent::new());
+    scene.add_component(entity, Health { points: 10 });
+
+    let entity_mut = scene.get_entity_mut_by_id(entity).unwrap();
+    let mut count = entity_mut.get_mut::<CountComponent>().unwrap();
+    let mut health = entity_mut.get_mut::<Health>().unwrap();
+    count.override_count(3);
+    health.points -= count.get_count() as i32;
+    drop(count);
+    drop(health);
+
+    assert_eq!(3, scene.get_component::<CountComponent>(entity).unwrap().get_count());
+    assert_eq!(7, scene.get_component::<Health>(entity).unwrap().points);
+}
+
+#[test]
+fn shared_borrows_and_reborrow_after_drop() {
+    let mut scene = Scene::new();
+    let entity = scene.create_entity();
+    scene.add_component(entity, Health { points: 10 });
+
+    let entity_mut = scene.get_entity_mut_by_id(entity).unwrap();
+    let first = entity_mut.get::<Health>().unwrap();
+    let second = entity_mut.get::<Health>().unwrap();
+    assert_eq!(first.points, second.points);
+    drop(first);
+    drop(second);
+
+    entity_mut.get_mut::<Health>().unwrap().points = 5;
+    assert_eq!(5, entity_mut.get::<Health>().unwrap().points);
+}
+
+#[test]
+#[should_panic(expected = "Health is already borrowed mutably")]
+fn get_while_borrowed_mutably() {
+    let mut scene = Scene::new();
+    let entity = scene.create_entity();
+    scene.add_component(entity, Health { points: 10 });
+
+    let entity_mut = scene.get_entity_mut_by_id(entity).unwrap();
+    let _health = entity_mut.get_mut::<Health>().unwrap();
+    entity_mut.get::<Health>();
+}
+
+#[test]
+#[should_panic(expected = "Health is already borrowed")]
+fn get_mut_while_borrowed() {
+    let mut scene = Scene::new();
+    let entity = scene.create_entity();
+    scene.add_component(entity, Health { points: 10 });
+
+    let entity_mut = scene.get_entity_mut_by_id(entity).unwrap();
+    let _health = entity_mut.get::<Health>().unwrap();
+    entity_mut.get_mut::<Health>();
+}
+
+#[test]
+fn entity_mut_changes_structure() {
+    let mut scene = Scene::new();
+    let entity = scene.create_entity();
+
+    let mut entity_mut = scene.get_entity_mut_by_id(entity).unwrap();
+    entity_mut.add_component(Health { points: 1 });
+    entity_mut.set_name(String::from("hero"));
+    assert!(entity_mut.has::<Health>());
+    assert!(entity_mut.remove_component::<Health>());
+    assert!(!entity_mut.has::<Health>());
+
+    assert_eq!("hero", scene.get_entity_ref_by_id(entity).unwrap().get_name());
+    scene.destroy_entity(entity);
+    assert!(scene.get_entity_ref_by_id(entity).is_none());
+    assert!(scene.get_entity_mut_by_id(entity).is_none());
+}
@@ -0,0 +1,107 @@
+use engine::event::EventReader;
+use engine::event::Events;
+use engine::resource::Time;
+use engine::scene::Scene;
+use engine::schedule::Stage;
+use engine::system::FunctionSystem;
+use engine::system::SystemContext;
+
+#[derive(Clone, Debug, PartialEq)]
+struct CollisionEvent {
+    first: u32,
+    second: u32,
+}
+
+#[derive(Debug, PartialEq)]
+struct Collisions(Vec<(u32, u32)>);
+
+fn collision(first: u32, second: u32) -> CollisionEvent {
+    CollisionEvent { first, second }
+}
+
+fn read_all(reader: &mut EventReader<CollisionEvent>, scene: &Scene) -> Vec<CollisionEvent> {
+    reader.read(scene.get_events::<CollisionEvent>().unwrap()).cloned().collect()
+}
+
+#[test]
+fn readers_see_each_event_once() {
+    let mut scene = Scene::new();
+    scene.add_event::<CollisionEvent>();
+    let mut first = EventReader::new();
+    let mut second = EventReader::new();
+
+    scene.get_event_writer::<CollisionEvent>().unwrap().send(collision(1, 2));
+    assert_eq!(vec![collision(1, 2)], read_all(&mut first, &scene));
+    assert!(read_all(&mut first, &scene).is_empty());
+
+    scene.get_event_writer::<CollisionEvent>().unwrap().send(collision(3, 4));
+    assert_eq!(vec![collision(3, 4)], read_all(&mut first, &scene));
+    assert_eq!(vec![collision(1, 2), collision(3, 4)], read_all(&mut second, &scene));
+}
+
+#[test]
+fn events_are_dropped_after_two_updates() {
+    let mut scene = Scene::new();
+    scene.add_event::<CollisionEvent>();
+    let mut late = EventReader::new();
+    let mut later = EventReader::new();
+
+    scene.get_event_writer::<CollisionEvent>().unwrap().send(collision(1, 2));
+    scene.update();
+    scene.get_event_writer::<CollisionEvent>().unwrap().send(collision(3, 4));
+    assert_eq!(vec![collision(1, 2), collision(3, 4)], read_all(&mut late, &scene));
+
+    scene.update();
+    assert_eq!(1, scene.get_events::<CollisionEvent>().unwrap().len());
+    assert_eq!(vec![collision(3, 4)], read_all(&mut later, &scene));
+
+    scene.update();
+    assert!(scene.get_events::<CollisionEvent>().unwrap().is_empty());
+    assert_eq!(2, scene.get_events::<CollisionEvent>().unwrap().get_event_count());
+}
+
+#[test]
+fn systems_send_and_receive_events() {
+    let mut scene = Scene::new();
+    scene.add_event::<CollisionEvent>();
+    scene.insert_resource(Collisions(vec![]));
+    let physics = FunctionSystem::new("physics", |context: &mut SystemContext| {
+        let frame = context.get_resource::<Time>().unwrap().get_frame_count() as u32;
+        context
+            .get_event_writer::<CollisionEvent>()
+            .unwrap()
+            .send(collision(frame, frame + 1));
+    })
+    .reads_resource::<Time>()
+    .writes_events::<CollisionEvent>();
+    let mut reader = EventReader::new();
+    let audio = FunctionSystem::new("audio", move |context: &mut SystemContext| {
+        let events: Vec<CollisionEvent> = reader.read(context.get_events().unwrap()).cloned().collect();
+        let collisions = context.get_resource_mut::<Collisions>().unwrap();
+        collisions.0.extend(events.iter().map(|x| (x.first, x.second)));
+    })
+    .reads_events::<CollisionEvent>()
+    .writes_resource::<Collisions>();
+    // Audio runs first and only sees the events of the previous frame
+    scene.add_system(Stage::Update, audio).before("physics");
+    scene.add_system(Stage::Update, physics);
+
+    scene.update();
+    scene.update();
+    scene.update();
+
+    assert_eq!(Some(&Collisions(vec![(1, 2), (2, 3)])), scene.get_resource::<Collisions>());
+}
+
+#[test]
+fn reader_skips_dropped_events() {
+    let mut events = Events::new();
+    let mut reader = EventReader::new();
+    events.send(collision(1, 2));
+    events.update();
+    events.update();
+    events.send(collision(3, 4));
+
+    let read: Vec<&CollisionEvent> = reader.read(&events).collect();
+    assert_eq!(vec![&collision(3, 4)], read);
+}
@@ -0,0 +1,141 @@
+use cgmath::Vector3;
+use engine::entity::EntityId;
+use engine::hierarchy::Children;
+use engine::hierarchy::Parent;
+use engine::scene::Scene;
+use engine::transform::GlobalTransform;
+use engine::transform::Transform;
+
+fn create_with_transform(scene: &mut Scene, x: f32) -> EntityId {
+    let entity = scene.create_entity();
+    scene.add_component(entity, Transform::from_translation(Vector3::new(x, 0.0, 0.0)));
+    entity
+}
+
+fn get_global_x(scene: &Scene, id: EntityId) -> f32 {
+    scene.get_component::<GlobalTransform>(id).unwrap().get_translation().x
+}
+
+#[test]
+fn reparenting_keeps_relations_consistent() {
+    let mut scene = Scene::new();
+    let ship = scene.create_entity();
+    let turret = scene.create_entity();
+    let gun = scene.create_entity();
+
+    assert!(scene.set_parent(turret, ship));
+    assert!(scene.set_parent(gun, turret));
+    assert_eq!(Some(ship), scene.get_parent(turret));
+    assert_eq!(&[turret], scene.get_children(ship));
+
+    assert!(scene.set_parent(gun, ship));
+    assert_eq!(&[turret, gun], scene.get_children(ship));
+    assert!(!scene.has_component::<Children>(turret));
+
+    assert!(scene.remove_parent(turret));
+    assert!(!scene.has_component::<Parent>(turret));
+    assert_eq!(&[gun], scene.get_children(ship));
+    assert!(!scene.remove_parent(turret));
+}
+
+#[test]
+fn cycles_are_rejected() {
+    let mut scene = Scene::new();
+    let root = scene.create_entity();
+    let child = scene.create_entity();
+    let grandchild = scene.create_entity();
+    scene.set_parent(child, root);
+    scene.set_parent(grandchild, child);
+
+    assert!(!scene.set_parent(root, grandchild));
+    assert!(!scene.set_parent(root, root));
+    assert_eq!(None, scene.get_parent(root));
+}
+
+#[test]
+fn destroying_parent_destroys_descendants() {
+    let mut scene = Scene::new();
+    let world = scene.create_entity();
+    let root = scene.create_entity();
+    let child = scene.create_entity();
+    let grandchild = scene.create_entity();
+    let sibling = scene.create_entity();
+    scene.set_parent(root, world);
+    scene.set_parent(child, root);
+    scene.set_parent(grandchild, child);
+    scene.set_parent(sibling, root);
+
+    assert!(scene.destroy_entity(root));
+
+    for id in [root, child, grandchild, sibling] {
+        assert!(!scene.contains_entity(id));
+    }
+    assert!(scene.contains_entity(world));
+    assert!(scene.get_children(world).is_empty());
+    assert_eq!(1, scene.get_entity_count());
+}
+
+#[test]
+fn transforms_propagate_top_down() {
+    let mut scene = Scene::new();
+    let root = create_with_transform(&mut scene, 1.0);
+    let child = create_with_transform(&mut scene, 2.0);
+    let grandchild = create_with_transform(&mut scene, 4.0);
+    scene.set_parent(child, root);
+    scene.set_parent(grandchild, child);
+
+    scene.update();
+
+    assert_eq!(1.0, get_global_x(&scene, root));
+    assert_eq!(3.0, get_global_x(&scene, child));
+    assert_eq!(7.0, get_global_x(&scene, grandchild));
+}
+
+#[test]
+fn unchanged_subtrees_are_skipped() {
+    let mut scene = Scene::new();
+    let root = create_with_transform(&mut scene, 1.0);
+    let first = create_with_transform(&mut scene, 2.0);
+    let second = create_with_transform(&mut scene, 4.0);
+    let leaf = create_with_transform(&mut scene, 8.0);
+    scene.set_parent(first, root);
+    scene.set_parent(second, root);
+    scene.set_parent(leaf, second);
+    assert_eq!(4, scene.propagate_transforms());
+    assert_eq!(0, scene.propagate_transforms());
+
+    scene
+        .get_component_mut::<Transform>(second)
+        .unwrap()
+        .set_translation(Vector3::new(16.0, 0.0, 0.0));
+    assert_eq!(2, scene.propagate_transforms());
+    assert_eq!(25.0, get_global_x(&scene, leaf));
+    assert_eq!(3.0, get_global_x(&scene, first));
+
+    scene.set_parent(leaf, first);
+    assert_eq!(1, scene.propagate_transforms());
+    assert_eq!(11.0, get_global_x(&scene, leaf));
+}
+
+#[test]
+fn removing_hierarchy_components_detaches_entities() {
+    let mut scene = Scene::new();
+    let root = create_with_transform(&mut scene, 1.0);
+    let first = create_with_transform(&mut scene, 2.0);
+    let second = create_with_transform(&mut scene, 4.0);
+    scene.set_parent(first, root);
+    scene.set_parent(second, root);
+    scene.propagate_transforms();
+
+    assert!(scene.remove_component::<Parent>(first));
+    assert_eq!(&[second], scene.get_children(root));
+    assert_eq!(None, scene.get_parent(first));
+    assert_eq!(1, scene.propagate_transforms());
+    assert_eq!(2.0, get_global_x(&scene, first));
+
+    let children = scene.take_component::<Children>(root).unwrap();
+    assert_eq!(&[second], children.get());
+    assert_eq!(None, scene.get_parent(second));
+    assert!(!scene.has_component::<Children>(root));
+    assert!(!scene.remove_component::<Children>(root));
+}
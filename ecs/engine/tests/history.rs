@@ -0,0 +1,257 @@
+use engine::entity::Component;
+use engine::observer::OnAdd;
+use engine::reflect::Reflect;
+use engine::relation::Relation;
+use engine::scene::Scene;
+use engine::transform::GlobalTransform;
+use engine::transform::Transform;
+use serde::Deserialize;
+use serde::Serialize;
+
+#[derive(Clone, Reflect)]
+struct Health {
+    current: u32,
+    title: String,
+}
+
+impl Component for Health {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Velocity {
+    speed: f32,
+}
+
+impl Component for Velocity {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+struct MeshHandle;
+
+impl Component for MeshHandle {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+struct Follows;
+
+impl Relation for Follows {}
+
+fn create_recording_scene() -> Scene {
+    let mut scene = Scene::new();
+    scene.get_component_registry_mut().register_clone::<Health>();
+    scene.get_component_registry_mut().register_reflect::<Health>();
+    scene.get_component_registry_mut().register::<Velocity>("Velocity");
+    scene.get_history_mut().set_recording(true);
+    scene
+}
+
+fn create_health() -> Health {
+    Health { current: 10, title: String::from("knight") }
+}
+
+#[test]
+fn single_edits_are_undone_and_redone() {
+    let mut scene = create_recording_scene();
+    let mut states = vec![scene.dump()];
+    let hero = scene.create_entity();
+    states.push(scene.dump());
+    scene.set_entity_name(hero, String::from("hero"));
+    states.push(scene.dump());
+    scene.add_component(hero, create_health());
+    states.push(scene.dump());
+    scene.set_field_text(hero, "Health.title", "squire").unwrap();
+    scene.set_field_text(hero, "Health.current", "4").unwrap();
+    states.push(scene.dump());
+    scene.remove_component::<Health>(hero);
+    states.push(scene.dump());
+    assert_eq!(Some("remove component"), scene.get_history().get_undo_name());
+
+    assert!(scene.undo());
+    assert_eq!(states[4], scene.dump());
+    assert!(scene.undo());
+    assert!(scene.undo());
+    assert_eq!(states[3], scene.dump());
+    assert!(scene.undo());
+    assert_eq!(states[2], scene.dump());
+    assert!(scene.undo());
+    assert_eq!(states[1], scene.dump());
+    assert!(scene.undo());
+    assert!(!scene.undo());
+    assert_eq!(states[0], scene.dump());
+
+    assert!(scene.redo());
+    assert_eq!(states[1], scene.dump());
+    assert_eq!(Some(hero), scene.get_entity_by_name("Entity 1"));
+    assert!(scene.redo());
+    assert!(scene.redo());
+    assert_eq!(states[3], scene.dump());
+    assert!(scene.redo());
+    assert!(scene.redo());
+    assert_eq!(states[4], scene.dump());
+    assert_eq!("\"squire\"", scene.get_field_text(hero, "Health.title").unwrap());
+    assert!(scene.redo());
+    assert!(!scene.redo());
+    assert_eq!(states[5], scene.dump());
+
+    assert!(scene.undo());
+    assert_eq!(4, scene.get_component::<Health>(hero).unwrap().current);
+    assert_eq!("squire", scene.get_component::<Health>(hero).unwrap().title);
+}
+
+#[test]
+fn destroyed_entities_come_back_with_their_ids() {
+    let mut scene = create_recording_scene();
+    let root = scene.create_entity_with_name(String::from("root"));
+    let first = scene.create_entity_with_name(String::from("first"));
+    let middle = scene.create_entity_with_name(String::from("middle"));
+    let last = scene.create_entity_with_name(String::from("last"));
+    let grandchild = scene.create_entity_with_name(String::from("grandchild"));
+    for child in [first, middle, last] {
+        scene.set_parent(child, root);
+    }
+    scene.set_parent(grandchild, middle);
+    scene.add_component(grandchild, create_health());
+    scene.add_relation::<Follows>(first, grandchild);
+    scene.add_relation::<Follows>(middle, root);
+    let before = scene.dump();
+
+    scene.destroy_entity(middle);
+    assert!(!scene.contains_entity(grandchild));
+    assert_eq!(vec![first, last], scene.get_children(root));
+    assert_eq!(Some("destroy entity"), scene.get_history().get_undo_name());
+
+    assert!(scene.undo());
+    assert_eq!(before, scene.dump());
+    assert_eq!(vec![first, middle, last], scene.get_children(root));
+    assert_eq!(vec![grandchild], scene.get_children(middle));
+    assert_eq!(10, scene.get_component::<Health>(grandchild).unwrap().current);
+    assert!(scene.has_relation::<Follows>(first, grandchild));
+    assert!(scene.has_relation::<Follows>(middle, root));
+
+    assert!(scene.redo());
+    assert!(!scene.contains_entity(middle));
+    assert!(!scene.contains_entity(grandchild));
+    assert!(scene.undo());
+    assert!(scene.undo());
+    assert!(!scene.has_relation::<Follows>(middle, root));
+    assert!(scene.contains_entity(grandchild));
+}
+
+#[test]
+fn transactions_are_undone_as_a_whole() {
+    let mut scene = create_recording_scene();
+    let hero = scene.create_entity_with_name(String::from("hero"));
+    let before = scene.dump();
+
+    scene.get_history_mut().begin_transaction("equip");
+    let sword = scene.create_entity_with_name(String::from("sword"));
+    scene.get_history_mut().begin_transaction("attach");
+    scene.set_parent(sword, hero);
+    scene.get_history_mut().end_transaction();
+    scene.add_component(hero, create_health());
+    scene.get_history_mut().end_transaction();
+    let equipped = scene.dump();
+    assert_eq!(Some("equip"), scene.get_history().get_undo_name());
+
+    assert!(scene.undo());
+    assert_eq!(before, scene.dump());
+    assert_eq!(Some("equip"), scene.get_history().get_redo_name());
+    assert!(scene.redo());
+    assert_eq!(equipped, scene.dump());
+    assert_eq!(vec![sword], scene.get_children(hero));
+
+    // A new edit drops the undone ones
+    scene.undo();
+    scene.set_entity_name(hero, String::from("villain"));
+    assert!(!scene.get_history().can_redo());
+    assert!(scene.undo());
+    assert!(scene.undo());
+    assert!(!scene.get_history().can_undo());
+    assert_eq!(0, scene.get_entity_count());
+}
+
+#[test]
+fn undoing_inside_a_transaction_leaves_it_open() {
+    let mut scene = create_recording_scene();
+    scene.get_history_mut().begin_transaction("summon");
+    let ghost = scene.create_entity();
+    assert!(scene.undo());
+    assert!(!scene.contains_entity(ghost));
+    let shade = scene.create_entity();
+    scene.get_history_mut().end_transaction();
+
+    assert_eq!(Some("summon"), scene.get_history().get_undo_name());
+    assert!(scene.undo());
+    assert!(!scene.contains_entity(shade));
+    assert!(!scene.get_history().can_undo());
+    assert!(scene.redo());
+    assert!(scene.contains_entity(shade));
+}
+
+#[test]
+fn irreversible_and_unrecorded_edits() {
+    let mut scene = create_recording_scene();
+    let hero = scene.create_entity();
+    scene.add_component(hero, MeshHandle);
+    assert!(!scene.get_history().can_undo());
+    assert!(scene.has_component::<MeshHandle>(hero));
+
+    scene.get_history_mut().set_recording(false);
+    scene.add_component(hero, create_health());
+    scene.get_history_mut().set_recording(true);
+    scene.get_component_mut::<Health>(hero).unwrap().current = 3;
+    assert!(!scene.get_history().can_undo());
+
+    scene.set_field_text(hero, "Health.current", "7").unwrap();
+    assert!(scene.undo());
+    assert_eq!(3, scene.get_component::<Health>(hero).unwrap().current);
+    assert!(!scene.undo());
+
+    // A NaN is saved as null, which can not be restored
+    scene.add_component(hero, Velocity { speed: 1.0 });
+    scene.add_component(hero, Velocity { speed: f32::NAN });
+    assert!(!scene.undo());
+    assert!(scene.get_component::<Velocity>(hero).unwrap().speed.is_nan());
+}
+
+#[test]
+fn edits_of_observers_are_not_recorded() {
+    let mut scene = create_recording_scene();
+    scene.observe::<OnAdd<Health>, _>(|id, scene| {
+        let name = format!("{} with health", scene.get_entity_by_id(id).unwrap().get_name());
+        scene.set_entity_name(id, name);
+    });
+    let hero = scene.create_entity_with_name(String::from("hero"));
+    scene.add_component(hero, create_health());
+    assert_eq!("hero with health", scene.get_entity_by_id(hero).unwrap().get_name());
+
+    assert!(scene.undo());
+    assert!(!scene.has_component::<Health>(hero));
+    assert_eq!("hero with health", scene.get_entity_by_id(hero).unwrap().get_name());
+    assert!(scene.undo());
+    assert!(!scene.contains_entity(hero));
+    assert!(!scene.get_history().can_undo());
+}
+
+#[test]
+fn updates_are_not_recorded() {
+    let mut scene = create_recording_scene();
+    let hero = scene.create_entity();
+    scene.add_component(hero, Transform::new());
+    scene.add_component(hero, create_health());
+    assert!(scene.get_history().can_undo());
+
+    // Propagating transforms adds the unregistered GlobalTransform
+    scene.update();
+    assert!(scene.has_component::<GlobalTransform>(hero));
+    assert!(scene.get_history().can_undo());
+    assert!(scene.undo());
+    assert!(!scene.has_component::<Health>(hero));
+}
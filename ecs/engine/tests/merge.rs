@@ -0,0 +1,126 @@
+use engine::entity::Component;
+use engine::entity::EntityId;
+use engine::query::Added;
+use engine::relation::Relation;
+use engine::scene::Scene;
+
+struct Health {
+    value: u32,
+}
+
+impl Component for Health {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+// Refers to another entity, which has to be fixed up with the id map after moving
+struct Follow {
+    target: EntityId,
+}
+
+impl Component for Follow {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+struct Guards;
+
+impl Relation for Guards {}
+
+fn get_names(scene: &Scene, ids: &[EntityId]) -> Vec<String> {
+    ids.iter().map(|x| scene.get_entity_by_id(*x).unwrap().get_name()).collect()
+}
+
+#[test]
+fn merged_entities_keep_names_components_and_hierarchy() {
+    let mut live = Scene::new();
+    let player = live.create_entity_with_name(String::from("player"));
+    live.add_component(player, Health { value: 10 });
+
+    let mut level = Scene::new();
+    let castle = level.create_entity_with_name(String::from("castle"));
+    let gate = level.create_entity_with_name(String::from("gate"));
+    let tower = level.create_entity_with_name(String::from("tower"));
+    level.set_parent(gate, castle);
+    level.set_parent(tower, castle);
+    level.add_component(gate, Health { value: 50 });
+    level.add_component(tower, Follow { target: gate });
+    level.add_relation::<Guards>(tower, gate);
+
+    let map = live.merge(level);
+
+    assert_eq!(3, map.len());
+    assert_eq!(4, live.get_entity_count());
+    assert_eq!(10, live.get_component::<Health>(player).unwrap().value);
+    let new_castle = live.get_entity_by_name("castle").unwrap();
+    let new_gate = map.get(gate).unwrap();
+    let new_tower = map.get(tower).unwrap();
+    assert_eq!(Some(new_castle), map.get(castle));
+    assert_eq!(vec!["gate", "tower"], get_names(&live, live.get_children(new_castle)));
+    assert_eq!(Some(new_castle), live.get_parent(new_gate));
+    assert_eq!(50, live.get_component::<Health>(new_gate).unwrap().value);
+    assert_eq!(&[new_gate], live.get_relation_targets::<Guards>(new_tower));
+
+    // Ids stored in components still refer to the old scene until they are mapped
+    let follow = live.get_component_mut::<Follow>(new_tower).unwrap();
+    assert_eq!(gate, follow.target);
+    follow.target = map.get(follow.target).unwrap();
+    assert_eq!(new_gate, live.get_component::<Follow>(new_tower).unwrap().target);
+}
+
+#[test]
+fn moved_entities_take_their_descendants() {
+    let mut staging = Scene::new();
+    let world = staging.create_entity_with_name(String::from("world"));
+    let house = staging.create_entity_with_name(String::from("house"));
+    let door = staging.create_entity_with_name(String::from("door"));
+    let tree = staging.create_entity_with_name(String::from("tree"));
+    staging.set_parent(house, world);
+    staging.set_parent(door, house);
+    staging.set_parent(tree, world);
+    staging.add_component(door, Health { value: 3 });
+    staging.add_relation::<Guards>(door, house);
+    staging.add_relation::<Guards>(tree, house);
+
+    let mut live = Scene::new();
+    live.update();
+    let map = staging.move_entity_to(&mut live, house);
+
+    assert_eq!(2, map.len());
+    assert!(!staging.contains_entity(house));
+    assert!(!staging.contains_entity(door));
+    assert_eq!(&[tree], staging.get_children(world));
+    assert!(staging.get_entity_by_name("house").is_none());
+    assert!(staging.get_relation_sources::<Guards>(house).is_empty());
+    assert_eq!(vec![door], staging.get_removed_components::<Health>().collect::<Vec<_>>());
+
+    let new_house = live.get_entity_by_name("house").unwrap();
+    let new_door = map.get(door).unwrap();
+    assert_eq!(None, live.get_parent(new_house));
+    assert_eq!(&[new_door], live.get_children(new_house));
+    assert_eq!(&[new_door], live.get_relation_sources::<Guards>(new_house));
+    assert_eq!(vec![new_door], live.query_filtered::<EntityId, Added<Health>>().collect::<Vec<_>>());
+}
+
+#[test]
+fn moving_reuses_free_ids_of_the_target() {
+    let mut live = Scene::new();
+    let first = live.create_entity();
+    let second = live.create_entity();
+    live.destroy_entity(first);
+
+    let mut staging = Scene::new();
+    let crate_id = staging.create_entity_with_name(String::from("crate"));
+    staging.add_component(crate_id, Health { value: 1 });
+    let map = staging.move_entity_to(&mut live, crate_id);
+
+    let moved = map.get(crate_id).unwrap();
+    assert_eq!(first.get_index(), moved.get_index());
+    assert_ne!(first, moved);
+    assert!(live.contains_entity(second));
+    assert_eq!(1, live.get_component::<Health>(moved).unwrap().value);
+    assert_eq!(0, staging.get_entity_count());
+    assert!(staging.move_entity_to(&mut live, crate_id).is_empty());
+}
@@ -0,0 +1,154 @@
+use engine::count_component::CountComponent;
+use engine::entity::Component;
+use engine::entity::EntityId;
+use engine::hierarchy::Parent;
+use engine::observer::OnAdd;
+use engine::observer::OnDespawn;
+use engine::observer::OnRemove;
+use engine::scene::Scene;
+use engine::schedule::Stage;
+use engine::system::FunctionSystem;
+use engine::system::SystemContext;
+
+struct Position {
+    x: f32,
+}
+
+impl Component for Position {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+// Stands in for an index kept outside of the components, like a spatial grid
+#[derive(Default)]
+struct Grid {
+    cells: Vec<(EntityId, f32)>,
+}
+
+#[derive(Default)]
+struct Log(Vec<String>);
+
+fn log(scene: &mut Scene, entry: String) {
+    scene.get_resource_mut::<Log>().unwrap().0.push(entry);
+}
+
+fn create_scene() -> Scene {
+    let mut scene = Scene::new();
+    scene.insert_resource(Grid::default());
+    scene.insert_resource(Log::default());
+    scene
+}
+
+fn take_log(scene: &mut Scene) -> Vec<String> {
+    std::mem::take(&mut scene.get_resource_mut::<Log>().unwrap().0)
+}
+
+#[test]
+fn observers_keep_an_index_in_sync() {
+    let mut scene = create_scene();
+    scene.observe::<OnAdd<Position>, _>(|entity, scene| {
+        let x = scene.get_component::<Position>(entity).unwrap().x;
+        scene.get_resource_mut::<Grid>().unwrap().cells.push((entity, x));
+    });
+    scene.observe::<OnRemove<Position>, _>(|entity, scene| {
+        scene.get_resource_mut::<Grid>().unwrap().cells.retain(|(id, _)| *id != entity);
+    });
+    scene.observe::<OnDespawn, _>(|entity, scene| {
+        assert!(!scene.contains_entity(entity));
+        log(scene, format!("despawned {}", entity.get_index()));
+    });
+    let first = scene.create_entity();
+    let second = scene.create_entity();
+
+    scene.add_component(first, Position { x: 1.0 });
+    scene.add_component(second, Position { x: 2.0 });
+    scene.add_component(first, Position { x: 3.0 });
+    assert_eq!(vec![(second, 2.0), (first, 3.0)], scene.get_resource::<Grid>().unwrap().cells);
+
+    scene.remove_component::<Position>(second);
+    scene.destroy_entity(first);
+    assert!(scene.get_resource::<Grid>().unwrap().cells.is_empty());
+    assert_eq!(vec!["despawned 0"], take_log(&mut scene));
+}
+
+#[test]
+fn commands_trigger_observers_when_applied() {
+    let mut scene = create_scene();
+    let target = scene.create_entity();
+    scene.observe::<OnAdd<CountComponent>, _>(|_, scene| log(scene, String::from("observed")));
+    let spawner = FunctionSystem::new("spawner", move |context: &mut SystemContext| {
+        context.get_commands().entity(target).add_component(CountComponent::new());
+        context.get_resource_mut::<Log>().unwrap().0.push(String::from("system"));
+    })
+    .writes_resource::<Log>();
+    scene.add_system(Stage::Update, spawner);
+
+    scene.update();
+
+    assert_eq!(vec!["system", "observed"], take_log(&mut scene));
+}
+
+#[test]
+fn observers_can_change_the_scene() {
+    let mut scene = create_scene();
+    scene.observe::<OnAdd<Position>, _>(|entity, scene| {
+        log(scene, String::from("position"));
+        scene.add_component(entity, CountComponent::new());
+        // Not observed again by itself
+        scene.add_component(entity, Position { x: 0.0 });
+    });
+    scene.observe::<OnAdd<CountComponent>, _>(|_, scene| log(scene, String::from("count")));
+    let entity = scene.create_entity();
+
+    scene.add_component(entity, Position { x: 5.0 });
+
+    assert_eq!(vec!["position", "count"], take_log(&mut scene));
+    assert_eq!(0.0, scene.get_component::<Position>(entity).unwrap().x);
+}
+
+#[test]
+fn hierarchy_changes_are_observed_once_consistent() {
+    let mut scene = create_scene();
+    scene.observe::<OnAdd<Parent>, _>(|entity, scene| {
+        let parent = scene.get_parent(entity).unwrap();
+        assert_eq!(&[entity], scene.get_children(parent));
+        log(scene, String::from("parented"));
+    });
+    let removed = scene.observe::<OnRemove<Parent>, _>(|_, scene| log(scene, String::from("orphaned")));
+    let parent = scene.create_entity();
+    let child = scene.create_entity();
+
+    scene.set_parent(child, parent);
+    scene.remove_parent(child);
+    assert!(scene.remove_observer(removed));
+    assert!(!scene.remove_observer(removed));
+    scene.set_parent(child, parent);
+    scene.destroy_entity(parent);
+
+    assert_eq!(vec!["parented", "orphaned", "parented"], take_log(&mut scene));
+}
+
+#[test]
+fn observers_can_destroy_entities_of_a_destroyed_tree() {
+    let mut scene = create_scene();
+    scene.observe::<OnRemove<Parent>, _>(|_, scene| {
+        if let Some(root) = scene.get_entity_by_name("root") {
+            assert!(scene.destroy_entity(root));
+        }
+    });
+    scene.observe::<OnDespawn, _>(|entity, scene| {
+        assert!(!scene.destroy_entity(entity));
+        log(scene, format!("despawned {}", entity.get_index()));
+    });
+    let root = scene.create_entity_with_name(String::from("root"));
+    let arm = scene.create_entity_with_name(String::from("arm"));
+    let hand = scene.create_entity_with_name(String::from("hand"));
+    scene.set_parent(arm, root);
+    scene.set_parent(hand, arm);
+
+    assert!(scene.destroy_entity(arm));
+
+    assert_eq!(0, scene.get_entity_count());
+    assert_eq!(vec!["despawned 0", "despawned 2", "despawned 1"], take_log(&mut scene));
+}
@@ -0,0 +1,190 @@
+use engine::entity::Component;
+use engine::prefab::Prefab;
+use engine::runner::ManualClock;
+use engine::runner::Runner;
+use engine::scene::Scene;
+use engine::schedule::Stage;
+use engine::snapshot::SceneSnapshot;
+use engine::system::FunctionSystem;
+use engine::system::SystemContext;
+
+struct Position {
+    x: f32,
+}
+
+struct Velocity {
+    x: f32,
+}
+
+struct Rotation {
+    angle: f32,
+}
+
+impl Component for Position {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+impl Component for Velocity {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+impl Component for Rotation {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+fn create_scene(parallel: bool) -> Scene {
+    let mut scene = Scene::new();
+    for i in 0..100 {
+        let entity = scene.create_entity();
+        scene.add_component(entity, Position { x: i as f32 });
+        scene.add_component(entity, Velocity { x: 1.0 });
+        if i % 2 == 0 {
+            scene.add_component(entity, Rotation { angle: 0.0 });
+        }
+    }
+    scene.get_schedule_mut().set_parallel(parallel);
+    scene.get_schedule_mut().set_debug(true);
+
+    let movement = FunctionSystem::new("movement", |context: &mut SystemContext| {
+        for (position, velocity) in context.query::<(&mut Position, &Velocity)>() {
+            position.x += velocity.x;
+        }
+    })
+    .writes::<Position>()
+    .reads::<Velocity>();
+    let spin = FunctionSystem::new("spin", |context: &mut SystemContext| {
+        for rotation in context.query::<&mut Rotation>() {
+            rotation.angle += 0.5;
+        }
+    })
+    .writes::<Rotation>();
+    let drag = FunctionSystem::new("drag", |context: &mut SystemContext| {
+        for (velocity, position) in context.query::<(&mut Velocity, &Position)>() {
+            velocity.x = position.x * 0.01;
+        }
+    })
+    .writes::<Velocity>()
+    .reads::<Position>();
+    scene.add_system(Stage::Update, movement);
+    scene.add_system(Stage::Update, spin);
+    scene.add_system(Stage::Update, drag);
+    scene
+}
+
+fn get_state(scene: &mut Scene) -> Vec<(f32, Option<f32>)> {
+    scene
+        .query::<(&Position, Option<&Rotation>)>()
+        .map(|(position, rotation)| (position.x, rotation.map(|x| x.angle)))
+        .collect()
+}
+
+#[test]
+fn scene_is_send_sync() {
+    assert_send_sync::<Scene>();
+    assert_send_sync::<SceneSnapshot>();
+    assert_send_sync::<Prefab>();
+    assert_send_sync::<Runner<ManualClock>>();
+}
+
+#[test]
+fn scene_moves_to_another_thread() {
+    let scene = create_scene(true);
+    let mut scene = std::thread::spawn(move || {
+        let mut scene = scene;
+        scene.update();
+        scene
+    })
+    .join()
+    .unwrap();
+    assert_eq!(100, scene.query::<&Position>().count());
+}
+
+#[test]
+fn scene_is_read_from_several_threads() {
+    let mut scene = create_scene(true);
+    scene.update();
+    let scene = &scene;
+    let sums: Vec<f32> = std::thread::scope(|scope| {
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                scope.spawn(move || {
+                    scene
+                        .get_entities()
+                        .filter_map(|x| scene.get_component::<Position>(x.get_id()))
+                        .map(|x| x.x)
+                        .sum()
+                })
+            })
+            .collect();
+        readers.into_iter().map(|x| x.join().unwrap()).collect()
+    });
+    let expected: f32 = (0..100).map(|x| x as f32 + 1.0).sum();
+    assert_eq!(vec![expected; 4], sums);
+}
+
+#[test]
+fn scene_is_built_on_a_loader_thread() {
+    let loader = std::thread::spawn(|| {
+        let mut scene = Scene::new();
+        let level = scene.create_entity_with_name(String::from("level"));
+        scene.add_component(level, Position { x: 1.0 });
+        scene
+    });
+    let mut runner = Runner::with_clock(loader.join().unwrap(), ManualClock::new());
+    runner.run_frame();
+    let scene = runner.into_scene();
+    let level = scene.get_entity_by_name("level").unwrap();
+    assert_eq!(1.0, scene.get_component::<Position>(level).unwrap().x);
+}
+
+#[test]
+fn conflicting_systems_are_serialized() {
+    let mut scene = create_scene(true);
+    scene.update();
+
+    let graph = scene.get_schedule().get_execution_graph().unwrap();
+    let batches = graph.get_batches(Stage::Update);
+    assert_eq!(vec![vec!["movement", "spin"], vec!["drag"]], batches);
+
+    let dot = graph.to_dot();
+    assert!(dot.starts_with("digraph frame {"));
+    assert!(dot.contains("\"Update/movement\" -> \"Update/drag\" [label=\"conflict: parallel::Position, parallel::Velocity\"];"));
+    assert!(!dot.contains("\"Update/spin\" ->"));
+}
+
+#[test]
+fn ordering_constraints_are_recorded() {
+    let mut scene = create_scene(true);
+    let log = FunctionSystem::new("log", |_: &mut SystemContext| {});
+    scene.add_system(Stage::Update, log).after("spin");
+    scene.update();
+
+    let graph = scene.get_schedule().get_execution_graph().unwrap();
+    assert_eq!(
+        vec![vec!["movement", "spin"], vec!["drag", "log"]],
+        graph.get_batches(Stage::Update)
+    );
+    assert!(graph.to_dot().contains("\"Update/spin\" -> \"Update/log\" [label=\"ordering\"];"));
+}
+
+#[test]
+fn parallel_matches_sequential() {
+    let mut parallel = create_scene(true);
+    let mut sequential = create_scene(false);
+    for _ in 0..10 {
+        parallel.update();
+        sequential.update();
+    }
+
+    assert_eq!(get_state(&mut sequential), get_state(&mut parallel));
+    let batches = sequential.get_schedule().get_execution_graph().unwrap().get_batches(Stage::Update);
+    assert_eq!(vec![vec!["movement"], vec!["spin"], vec!["drag"]], batches);
+}
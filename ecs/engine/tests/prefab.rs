@@ -0,0 +1,208 @@
+use engine::count_component::CountComponent;
+use engine::entity::Component;
+use engine::entity::EntityId;
+use engine::observer::OnAdd;
+use engine::prefab::Prefab;
+use engine::prefab::PrefabOverrides;
+use engine::scene::Scene;
+use engine::serialization::SceneError;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::json;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+struct Health {
+    current: u32,
+    max: u32,
+}
+
+impl Component for Health {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+const TURRET: &str = r#"{
+  "entities": [
+    { "id": { "index": 0, "generation": 0 }, "name": "turret",
+      "components": { "Health": { "current": 10, "max": 10 } } },
+    { "id": { "index": 1, "generation": 0 }, "name": "barrel",
+      "parent": { "index": 0, "generation": 0 },
+      "components": { "CountComponent": { "count": 0 } } },
+    { "id": { "index": 2, "generation": 0 }, "name": "sight",
+      "parent": { "index": 1, "generation": 0 },
+      "components": {} }
+  ]
+}"#;
+
+fn get_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("engine-prefab-{}-{}.json", name, std::process::id()))
+}
+
+fn create_registered_scene() -> Scene {
+    let mut scene = Scene::new();
+    scene.get_component_registry_mut().register::<CountComponent>("CountComponent");
+    scene.get_component_registry_mut().register::<Health>("Health");
+    scene
+}
+
+fn load_prefab(scene: &Scene, name: &str, text: &str) -> Result<Prefab, SceneError> {
+    let path = get_path(name);
+    std::fs::write(&path, text).unwrap();
+    let result = scene.load_prefab(&path);
+    std::fs::remove_file(&path).unwrap();
+    result
+}
+
+fn get_child(scene: &Scene, parent: EntityId, name: &str) -> EntityId {
+    let mut children = scene.get_children(parent).iter();
+    *children.find(|x| scene.get_entity_by_id(**x).unwrap().get_name() == name).unwrap()
+}
+
+#[test]
+fn spawned_copies_are_independent() {
+    let mut scene = Scene::new();
+    let cat = Prefab::new("cat")
+        .with_component(CountComponent::new())
+        .with_child(Prefab::new("tail").with_component(Health { current: 1, max: 1 }));
+
+    let first = scene.spawn_prefab(&cat).unwrap();
+    scene.update();
+    let second = scene.spawn_prefab(&cat).unwrap();
+
+    assert_eq!(4, scene.get_entity_count());
+    assert_eq!(vec![first, second], scene.get_entities_by_name("cat").collect::<Vec<_>>());
+    assert_eq!(1, scene.get_component::<CountComponent>(first).unwrap().get_count());
+    assert_eq!(0, scene.get_component::<CountComponent>(second).unwrap().get_count());
+    let tail = get_child(&scene, second, "tail");
+    assert_eq!(Some(&Health { current: 1, max: 1 }), scene.get_component::<Health>(tail));
+    assert_ne!(get_child(&scene, first, "tail"), tail);
+}
+
+#[test]
+fn loaded_prefabs_apply_overrides() {
+    let mut scene = create_registered_scene();
+    let turret = load_prefab(&scene, "overrides", TURRET).unwrap();
+    assert_eq!("turret", turret.get_name());
+    assert_eq!("barrel", turret.get_children()[0].get_name());
+
+    let mut overrides = PrefabOverrides::new();
+    overrides
+        .set_field("", "Health", "current", json!(4))
+        .set_field("barrel", "CountComponent", "count", json!(7));
+    let damaged = scene.spawn_prefab_with_overrides(&turret, &overrides).unwrap();
+    let fresh = scene.spawn_prefab(&turret).unwrap();
+
+    assert_eq!(Some(&Health { current: 4, max: 10 }), scene.get_component::<Health>(damaged));
+    assert_eq!(Some(&Health { current: 10, max: 10 }), scene.get_component::<Health>(fresh));
+    let barrel = get_child(&scene, damaged, "barrel");
+    assert_eq!(7, scene.get_component::<CountComponent>(barrel).unwrap().get_count());
+    let sight = get_child(&scene, barrel, "sight");
+    assert_eq!(Some(barrel), scene.get_parent(sight));
+}
+
+#[test]
+fn overrides_pick_children_by_index_and_write_in_place() {
+    let mut scene = create_registered_scene();
+    let added = Arc::new(AtomicUsize::new(0));
+    let counter = added.clone();
+    scene.observe::<OnAdd<Health>, _>(move |_, _| {
+        counter.fetch_add(1, Ordering::SeqCst);
+    });
+    let crab = Prefab::new("crab")
+        .with_child(Prefab::new("claw").with_component(Health { current: 2, max: 2 }))
+        .with_child(Prefab::new("claw").with_component(Health { current: 2, max: 2 }));
+
+    let mut overrides = PrefabOverrides::new();
+    overrides.set_field("claw#1", "Health", "current", json!(1));
+    let hurt = scene.spawn_prefab_with_overrides(&crab, &overrides).unwrap();
+    let claws = scene.get_children(hurt);
+    assert_eq!(2, scene.get_component::<Health>(claws[0]).unwrap().current);
+    assert_eq!(1, scene.get_component::<Health>(claws[1]).unwrap().current);
+    assert_eq!(2, added.load(Ordering::SeqCst));
+
+    for path in ["claw", "claw#2"] {
+        let mut overrides = PrefabOverrides::new();
+        overrides.set_field(path, "Health", "current", json!(1));
+        match scene.spawn_prefab_with_overrides(&crab, &overrides) {
+            Err(SceneError::InvalidOverride(_)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+    assert_eq!(3, scene.get_entity_count());
+}
+
+#[test]
+fn failed_overrides_spawn_nothing() {
+    let mut scene = create_registered_scene();
+    let turret = load_prefab(&scene, "failed", TURRET).unwrap();
+    let invalid = [
+        ("barrel/scope", "Health", "max"),
+        ("barrel", "Health", "max"),
+        ("", "Health", "armor"),
+        ("", "Shield", "strength"),
+    ];
+
+    for (path, component, field) in invalid.iter() {
+        let mut overrides = PrefabOverrides::new();
+        overrides.set_field(path, component, field, json!(1));
+        assert!(scene.spawn_prefab_with_overrides(&turret, &overrides).is_err());
+    }
+    let mut overrides = PrefabOverrides::new();
+    overrides.set_field("", "Health", "max", json!("many"));
+    match scene.spawn_prefab_with_overrides(&turret, &overrides) {
+        Err(SceneError::Format(_)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert_eq!(0, scene.get_entity_count());
+}
+
+#[test]
+fn undone_spawns_come_back_with_their_overrides() {
+    let mut scene = create_registered_scene();
+    scene.get_history_mut().set_recording(true);
+    let turret = load_prefab(&scene, "history", TURRET).unwrap();
+    let mut overrides = PrefabOverrides::new();
+    overrides
+        .set_field("", "Health", "current", json!(4))
+        .set_field("barrel", "CountComponent", "count", json!(7));
+    let damaged = scene.spawn_prefab_with_overrides(&turret, &overrides).unwrap();
+    assert_eq!(Some("spawn prefab"), scene.get_history().get_undo_name());
+
+    assert!(scene.undo());
+    assert_eq!(0, scene.get_entity_count());
+    // A spawn that fails leaves the undone spawn to be redone
+    let mut invalid = PrefabOverrides::new();
+    invalid.set_field("", "Health", "armor", json!(1));
+    assert!(scene.spawn_prefab_with_overrides(&turret, &invalid).is_err());
+    assert!(scene.get_history().can_redo());
+
+    assert!(scene.redo());
+    assert_eq!(3, scene.get_entity_count());
+    assert_eq!(Some(&Health { current: 4, max: 10 }), scene.get_component::<Health>(damaged));
+    let barrel = get_child(&scene, damaged, "barrel");
+    assert_eq!(7, scene.get_component::<CountComponent>(barrel).unwrap().get_count());
+    assert_eq!(Some(barrel), scene.get_parent(get_child(&scene, barrel, "sight")));
+}
+
+#[test]
+fn invalid_prefab_files_are_rejected() {
+    let scene = create_registered_scene();
+    let two_roots = TURRET.replace(r#""parent": { "index": 0, "generation": 0 },"#, "");
+    match load_prefab(&scene, "roots", &two_roots) {
+        Err(SceneError::PrefabRootCount(2)) => {}
+        Err(other) => panic!("unexpected error {:?}", other),
+        Ok(_) => panic!("prefab with two roots was loaded"),
+    }
+
+    let unregistered = load_prefab(&Scene::new(), "unregistered", TURRET);
+    match unregistered {
+        Err(SceneError::UnknownComponent(name)) => assert_eq!("Health", name),
+        Err(other) => panic!("unexpected error {:?}", other),
+        Ok(_) => panic!("prefab with unregistered components was loaded"),
+    }
+}
@@ -0,0 +1,113 @@
+use engine::count_component::CountComponent;
+use engine::entity::Component;
+use engine::entity::EntityId;
+use engine::query::With;
+use engine::query::Without;
+use engine::scene::Scene;
+
+struct Position {
+    x: f32,
+}
+
+struct Velocity {
+    x: f32,
+}
+
+struct Frozen;
+
+impl Component for Position {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+impl Component for Velocity {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+impl Component for Frozen {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+fn spawn(scene: &mut Scene, x: f32, velocity: Option<f32>, frozen: bool) -> EntityId {
+    let entity = scene.create_entity();
+    scene.add_component(entity, Position { x });
+    if let Some(velocity) = velocity {
+        scene.add_component(entity, Velocity { x: velocity });
+    }
+    if frozen {
+        scene.add_component(entity, Frozen);
+    }
+    entity
+}
+
+#[test]
+fn query_single_component() {
+    let mut scene = Scene::new();
+    spawn(&mut scene, 1.0, None, false);
+    spawn(&mut scene, 2.0, Some(1.0), false);
+    spawn(&mut scene, 3.0, Some(1.0), true);
+
+    let sum: f32 = scene.query::<&Position>().map(|position| position.x).sum();
+    assert_eq!(6.0, sum);
+    assert_eq!(0, scene.query::<&CountComponent>().count());
+}
+
+#[test]
+fn query_writes_components() {
+    let mut scene = Scene::new();
+    let still = spawn(&mut scene, 1.0, None, false);
+    let moving = spawn(&mut scene, 2.0, Some(3.0), false);
+
+    for (position, velocity) in scene.query::<(&mut Position, &Velocity)>() {
+        position.x += velocity.x;
+    }
+
+    assert_eq!(1.0, scene.get_component::<Position>(still).unwrap().x);
+    assert_eq!(5.0, scene.get_component::<Position>(moving).unwrap().x);
+}
+
+#[test]
+fn query_entity_and_optional_component() {
+    let mut scene = Scene::new();
+    let still = spawn(&mut scene, 1.0, None, false);
+    let moving = spawn(&mut scene, 2.0, Some(3.0), false);
+
+    let mut results: Vec<(EntityId, Option<f32>)> = scene
+        .query::<(EntityId, Option<&Velocity>)>()
+        .map(|(entity, velocity)| (entity, velocity.map(|x| x.x)))
+        .collect();
+    results.sort_by_key(|(entity, _)| entity.get_index());
+    assert_eq!(vec![(still, None), (moving, Some(3.0))], results);
+}
+
+#[test]
+fn query_filtered_with_and_without() {
+    let mut scene = Scene::new();
+    spawn(&mut scene, 1.0, None, false);
+    let moving = spawn(&mut scene, 2.0, Some(1.0), false);
+    spawn(&mut scene, 3.0, Some(1.0), true);
+
+    let found: Vec<EntityId> = scene.query_filtered::<EntityId, (With<Velocity>, Without<Frozen>)>().collect();
+    assert_eq!(vec![moving], found);
+}
+
+#[test]
+#[should_panic(expected = "accessed both mutably and immutably")]
+fn query_conflicting_access() {
+    let mut scene = Scene::new();
+    spawn(&mut scene, 1.0, None, false);
+    scene.query::<(&mut Position, &Position)>().count();
+}
+
+#[test]
+#[should_panic(expected = "accessed mutably more than once")]
+fn query_duplicate_mutable_access() {
+    let mut scene = Scene::new();
+    spawn(&mut scene, 1.0, None, false);
+    scene.query::<(&mut Position, &mut Position)>().count();
+}
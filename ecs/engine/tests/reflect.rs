@@ -0,0 +1,128 @@
+use engine::count_component::CountComponent;
+use engine::entity::Component;
+use engine::entity::EntityId;
+use engine::query::Changed;
+use engine::reflect;
+use engine::reflect::FieldInfo;
+use engine::reflect::Reflect;
+use engine::reflect::ReflectError;
+use engine::scene::Scene;
+
+#[derive(Reflect)]
+struct Health {
+    current: u32,
+    max: f32,
+}
+
+#[derive(Reflect)]
+struct Speed(f32);
+
+#[derive(Reflect)]
+struct Stats {
+    health: Health,
+    title: String,
+    #[reflect(ignore)]
+    _cache: Vec<u8>,
+}
+
+impl Component for Stats {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+struct MeshHandle;
+
+impl Component for MeshHandle {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+fn create_stats() -> Stats {
+    Stats {
+        health: Health { current: 3, max: 10.0 },
+        title: String::from("knight"),
+        _cache: vec![],
+    }
+}
+
+fn create_registered_scene() -> (Scene, EntityId) {
+    let mut scene = Scene::new();
+    scene.get_component_registry_mut().register_reflect::<CountComponent>();
+    scene.get_component_registry_mut().register_reflect::<Stats>();
+    let hero = scene.create_entity_with_name(String::from("hero"));
+    scene.add_component(hero, create_stats());
+    scene.add_component(hero, CountComponent::new());
+    scene.add_component(hero, MeshHandle);
+    (scene, hero)
+}
+
+#[test]
+fn derived_fields_are_listed_and_accessed() {
+    let mut stats = create_stats();
+    assert_eq!("Stats", stats.get_type_name());
+    let expected = vec![
+        FieldInfo::new("health", "reflect::Health"),
+        FieldInfo::new("title", "alloc::string::String"),
+    ];
+    assert_eq!(expected, stats.get_fields());
+    assert_eq!("3", reflect::get_path(&stats, "health.current").unwrap().get_text());
+    assert!(reflect::get_path(&stats, "health.armor").is_none());
+    assert!(reflect::get_path(&stats, "_cache").is_none());
+
+    let max = reflect::get_path_mut(&mut stats, "health.max").unwrap();
+    assert!(max.set_text("12.5"));
+    assert!(!max.set_text("lots"));
+    assert_eq!(12.5, stats.health.max);
+    assert_eq!(
+        r#"Stats { health: Health { current: 3, max: 12.5 }, title: "knight" }"#,
+        stats.get_text()
+    );
+
+    let mut speed = Speed(1.0);
+    assert!(speed.get_field_mut("0").unwrap().set_text("2"));
+    assert_eq!("Speed { 0: 2.0 }", speed.get_text());
+}
+
+#[test]
+fn scene_fields_are_read_and_written_by_path() {
+    let (mut scene, hero) = create_registered_scene();
+    scene.update();
+
+    assert_eq!("1", scene.get_field_text(hero, "CountComponent.count").unwrap());
+    scene.set_field_text(hero, "Stats.title", "paladin").unwrap();
+    assert_eq!("\"paladin\"", scene.get_field_text(hero, "Stats.title").unwrap());
+    assert_eq!(2, scene.get_reflected_components(hero).len());
+
+    // Only the written component is marked as changed
+    let changed: Vec<EntityId> = scene.query_filtered::<EntityId, Changed<Stats>>().collect();
+    assert_eq!(vec![hero], changed);
+    assert_eq!(0, scene.query_filtered::<EntityId, Changed<CountComponent>>().count());
+
+    let missing = Err(ReflectError::MissingComponent(hero, String::from("MeshHandle")));
+    assert_eq!(missing, scene.get_field_text(hero, "MeshHandle"));
+    let invalid = Err(ReflectError::InvalidValue(String::from("Stats.health.current"), String::from("-1")));
+    assert_eq!(invalid, scene.set_field_text(hero, "Stats.health.current", "-1"));
+    let missing_field = Err(ReflectError::MissingField(String::from("Stats.mana")));
+    assert_eq!(missing_field, scene.get_field_text(hero, "Stats.mana"));
+}
+
+#[test]
+fn dump_lists_entities_and_fields() {
+    let (mut scene, hero) = create_registered_scene();
+    let sword = scene.create_entity_with_name(String::from("sword"));
+    scene.set_parent(sword, hero);
+
+    let expected = [
+        "hero [0.0]",
+        "    CountComponent { count: 0 }",
+        "    Stats { health: Health { current: 3, max: 10.0 }, title: \"knight\" }",
+        "    engine::hierarchy::Children",
+        "    reflect::MeshHandle",
+        "sword [1.0]",
+        "    engine::hierarchy::Parent",
+        "",
+    ];
+    assert_eq!(expected.join("\n"), scene.dump());
+}
@@ -0,0 +1,121 @@
+use engine::command::Commands;
+use engine::entity::EntityId;
+use engine::relation::Relation;
+use engine::scene::Scene;
+use engine::schedule::Stage;
+use engine::system::FunctionSystem;
+use engine::system::SystemContext;
+
+struct Targets;
+
+impl Relation for Targets {}
+
+struct Owns;
+
+impl Relation for Owns {}
+
+#[test]
+fn relations_are_queried_from_both_sides() {
+    let mut scene = Scene::new();
+    let archer = scene.create_entity();
+    let knight = scene.create_entity();
+    let goblin = scene.create_entity();
+    let troll = scene.create_entity();
+
+    assert!(scene.add_relation::<Targets>(archer, goblin));
+    assert!(scene.add_relation::<Targets>(archer, troll));
+    assert!(scene.add_relation::<Targets>(knight, troll));
+    assert!(!scene.add_relation::<Targets>(archer, goblin));
+    assert!(scene.add_relation::<Owns>(knight, archer));
+
+    assert_eq!(&[goblin, troll], scene.get_relation_targets::<Targets>(archer));
+    assert_eq!(&[archer, knight], scene.get_relation_sources::<Targets>(troll));
+    assert_eq!(&[knight], scene.get_relation_sources::<Owns>(archer));
+    assert!(scene.get_relation_targets::<Owns>(archer).is_empty());
+    assert!(scene.has_relation::<Owns>(knight, archer));
+    assert!(!scene.has_relation::<Targets>(knight, archer));
+    let mut pairs: Vec<(EntityId, EntityId)> = scene.get_relation_pairs::<Targets>().collect();
+    pairs.sort_by_key(|(source, target)| (source.get_index(), target.get_index()));
+    assert_eq!(vec![(archer, goblin), (archer, troll), (knight, troll)], pairs);
+
+    assert!(scene.remove_relation::<Targets>(archer, troll));
+    assert!(!scene.remove_relation::<Targets>(archer, troll));
+    assert_eq!(&[goblin], scene.get_relation_targets::<Targets>(archer));
+    assert_eq!(&[knight], scene.get_relation_sources::<Targets>(troll));
+}
+
+#[test]
+fn destroying_either_side_removes_its_relations() {
+    let mut scene = Scene::new();
+    let archer = scene.create_entity();
+    let knight = scene.create_entity();
+    let goblin = scene.create_entity();
+    scene.add_relation::<Targets>(archer, goblin);
+    scene.add_relation::<Targets>(knight, goblin);
+    scene.add_relation::<Owns>(goblin, archer);
+
+    scene.destroy_entity(goblin);
+    assert!(scene.get_relation_targets::<Targets>(archer).is_empty());
+    assert!(scene.get_relation_sources::<Owns>(archer).is_empty());
+    assert_eq!(0, scene.get_relation_pairs::<Targets>().count());
+
+    // The destroyed entity's index is reused without inheriting its relations
+    let orc = scene.create_entity();
+    assert_eq!(goblin.get_index(), orc.get_index());
+    assert!(scene.get_relation_sources::<Targets>(orc).is_empty());
+    assert!(!scene.add_relation::<Targets>(archer, goblin));
+
+    scene.add_relation::<Targets>(archer, orc);
+    scene.destroy_entity(archer);
+    assert!(scene.get_relation_sources::<Targets>(orc).is_empty());
+}
+
+#[test]
+fn systems_read_relations_and_change_them_through_commands() {
+    let mut scene = Scene::new();
+    let archer = scene.create_entity();
+    let goblin = scene.create_entity();
+    scene.insert_resource(Vec::<EntityId>::new());
+    let retarget = FunctionSystem::new("retarget", move |context: &mut SystemContext| {
+        let targets = context.get_relation_targets::<Targets>(archer).to_vec();
+        context.get_resource_mut::<Vec<EntityId>>().unwrap().extend(targets.iter().copied());
+        let commands = context.get_commands();
+        for target in targets {
+            commands.entity(archer).remove_relation::<Targets, _>(target);
+        }
+        let spawned = commands.spawn().get_target();
+        commands.entity(archer).add_relation::<Targets, _>(spawned);
+    })
+    .writes_resource::<Vec<EntityId>>();
+    scene.add_system(Stage::Update, retarget);
+    scene.add_relation::<Targets>(archer, goblin);
+
+    scene.update();
+    assert_eq!(&[goblin], scene.get_resource::<Vec<EntityId>>().unwrap().as_slice());
+    let spawned = scene.get_relation_targets::<Targets>(archer).to_vec();
+    assert_eq!(1, spawned.len());
+    assert_ne!(goblin, spawned[0]);
+    assert!(scene.get_relation_sources::<Targets>(goblin).is_empty());
+
+    let mut commands = Commands::new();
+    commands.despawn(spawned[0]);
+    scene.apply_commands(&mut commands);
+    assert!(scene.get_relation_targets::<Targets>(archer).is_empty());
+}
+
+#[test]
+fn snapshots_restore_relations() {
+    let mut scene = Scene::new();
+    let archer = scene.create_entity();
+    let goblin = scene.create_entity();
+    scene.add_relation::<Targets>(archer, goblin);
+    let snapshot = scene.snapshot();
+
+    scene.destroy_entity(goblin);
+    let troll = scene.create_entity();
+    scene.add_relation::<Targets>(archer, troll);
+
+    scene.restore(&snapshot);
+    assert_eq!(&[goblin], scene.get_relation_targets::<Targets>(archer));
+    assert_eq!(&[archer], scene.get_relation_sources::<Targets>(goblin));
+}
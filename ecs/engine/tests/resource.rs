@@ -0,0 +1,160 @@
+use engine::entity::Component;
+use engine::entity::UpdateContext;
+use engine::resource::FixedTime;
+use engine::resource::Time;
+use engine::scene::Scene;
+use engine::schedule::Stage;
+use engine::system::FunctionSystem;
+use engine::system::SystemContext;
+use std::time::Duration;
+use std::time::Instant;
+
+#[derive(Debug, PartialEq)]
+struct Score(u32);
+
+#[derive(Default)]
+struct Gravity(f32);
+
+struct Falling {
+    speed: f32,
+}
+
+impl Component for Falling {
+    fn update(&mut self, context: &mut UpdateContext) {
+        self.speed += context.get_resources().get::<Gravity>().unwrap().0;
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[test]
+fn insert_get_and_remove() {
+    let mut scene = Scene::new();
+    assert!(!scene.has_resource::<Score>());
+    assert_eq!(None, scene.insert_resource(Score(1)));
+    assert_eq!(Some(Score(1)), scene.insert_resource(Score(2)));
+
+    scene.get_resource_mut::<Score>().unwrap().0 += 3;
+    assert_eq!(Some(&Score(5)), scene.get_resource::<Score>());
+
+    assert_eq!(Some(Score(5)), scene.remove_resource::<Score>());
+    assert_eq!(None, scene.get_resource::<Score>());
+    assert_eq!(None, scene.remove_resource::<Score>());
+}
+
+#[test]
+fn systems_use_resources() {
+    let mut scene = Scene::new();
+    scene.insert_resource(Score(0));
+    scene.insert_resource(Gravity(2.0));
+    let scoring = FunctionSystem::new("scoring", |context: &mut SystemContext| {
+        let points = context.get_resource::<Gravity>().unwrap().0 as u32;
+        context.get_resource_mut::<Score>().unwrap().0 += points;
+    })
+    .reads_resource::<Gravity>()
+    .writes_resource::<Score>();
+    scene.add_system(Stage::Update, scoring);
+
+    scene.update();
+    scene.update();
+
+    assert_eq!(Some(&Score(4)), scene.get_resource::<Score>());
+}
+
+#[test]
+#[should_panic(expected = "system sneaky accesses resource::Score without declaring it")]
+fn undeclared_resource_access() {
+    let mut scene = Scene::new();
+    scene.insert_resource(Score(0));
+    let sneaky = FunctionSystem::new("sneaky", |context: &mut SystemContext| {
+        context.get_resource_mut::<Score>().unwrap().0 += 1;
+    })
+    .reads_resource::<Score>();
+    scene.add_system(Stage::Update, sneaky);
+    scene.update();
+}
+
+#[test]
+fn resource_writers_do_not_run_in_parallel() {
+    let mut scene = Scene::new();
+    scene.get_schedule_mut().set_debug(true);
+    scene.insert_resource(Score(0));
+    let increment = |name: &'static str| {
+        FunctionSystem::new(name, |context: &mut SystemContext| {
+            context.get_resource_mut::<Score>().unwrap().0 += 1;
+        })
+        .writes_resource::<Score>()
+    };
+    scene.add_system(Stage::Update, increment("first"));
+    scene.add_system(Stage::Update, increment("second"));
+
+    scene.update();
+
+    let batches = scene.get_schedule().get_execution_graph().unwrap().get_batches(Stage::Update);
+    assert_eq!(vec![vec!["first"], vec!["second"]], batches);
+    assert_eq!(Some(&Score(2)), scene.get_resource::<Score>());
+}
+
+#[test]
+fn component_updates_read_resources() {
+    let mut scene = Scene::new();
+    scene.insert_resource(Gravity(1.5));
+    let entity = scene.create_entity();
+    scene.add_component(entity, Falling { speed: 0.0 });
+
+    scene.update();
+    scene.update();
+
+    assert_eq!(3.0, scene.get_component::<Falling>(entity).unwrap().speed);
+}
+
+#[test]
+fn time_is_advanced_by_update() {
+    let mut scene = Scene::new();
+    // Long enough that no fixed step is expended
+    scene.insert_resource(FixedTime::new(Duration::from_secs(60)));
+    assert_eq!(0, scene.get_resource::<Time>().unwrap().get_frame_count());
+
+    scene.update();
+    let time = *scene.get_resource::<Time>().unwrap();
+    assert_eq!(1, time.get_frame_count());
+    assert_eq!(Duration::ZERO, time.get_delta());
+
+    std::thread::sleep(Duration::from_millis(5));
+    scene.update();
+    let time = *scene.get_resource::<Time>().unwrap();
+    assert_eq!(2, time.get_frame_count());
+    assert!(time.get_delta() >= Duration::from_millis(5));
+    assert_eq!(time.get_delta(), time.get_elapsed());
+    assert_eq!(time.get_elapsed(), scene.get_resource::<FixedTime>().unwrap().get_accumulator());
+}
+
+#[test]
+fn fixed_time_expends_whole_steps() {
+    let mut fixed_time = FixedTime::new(Duration::from_millis(10));
+    fixed_time.accumulate(Duration::from_millis(25));
+
+    assert!(fixed_time.expend());
+    assert!(fixed_time.expend());
+    assert!(!fixed_time.expend());
+    assert_eq!(Duration::from_millis(5), fixed_time.get_accumulator());
+    assert_eq!(0.5, fixed_time.get_overstep());
+}
+
+#[test]
+fn long_stalls_drop_all_whole_steps() {
+    let mut scene = Scene::new();
+    let mut fixed_time = FixedTime::new(Duration::from_nanos(2));
+    fixed_time.set_max_steps(1);
+    scene.insert_resource(fixed_time);
+    let start = Instant::now();
+    scene.update_at(start);
+
+    // More steps than a u32 can count
+    scene.update_at(start + Duration::from_secs(10) + Duration::from_nanos(1));
+    let fixed_time = scene.get_resource::<FixedTime>().unwrap();
+    assert_eq!(1, fixed_time.get_frame_steps());
+    assert_eq!(Duration::from_nanos(1), fixed_time.get_accumulator());
+}
@@ -0,0 +1,84 @@
+use engine::resource::FixedTime;
+use engine::resource::Time;
+use engine::runner::ManualClock;
+use engine::runner::Runner;
+use engine::scene::Scene;
+use engine::schedule::Stage;
+use engine::system::FunctionSystem;
+use engine::system::SystemContext;
+use std::time::Duration;
+
+#[derive(Default)]
+struct Steps(u32);
+
+#[derive(Default)]
+struct RenderedAlpha(f32);
+
+fn create_runner() -> Runner<ManualClock> {
+    let mut scene = Scene::new();
+    scene.insert_resource(Steps::default());
+    scene.insert_resource(RenderedAlpha::default());
+    let step = FunctionSystem::new("step", |context: &mut SystemContext| {
+        context.get_resource_mut::<Steps>().unwrap().0 += 1;
+    })
+    .writes_resource::<Steps>();
+    let render = FunctionSystem::new("render", |context: &mut SystemContext| {
+        let alpha = context.get_resource::<FixedTime>().unwrap().get_overstep();
+        context.get_resource_mut::<RenderedAlpha>().unwrap().0 = alpha;
+    })
+    .reads_resource::<FixedTime>()
+    .writes_resource::<RenderedAlpha>();
+    scene.add_system(Stage::FixedUpdate, step);
+    scene.add_system(Stage::Render, render);
+    let mut runner = Runner::with_clock(scene, ManualClock::new());
+    runner.set_tick_rate(10);
+    runner
+}
+
+fn get_steps(runner: &Runner<ManualClock>) -> u32 {
+    runner.get_scene().get_resource::<Steps>().unwrap().0
+}
+
+#[test]
+fn fixed_steps_follow_the_clock() {
+    let mut runner = create_runner();
+    assert_eq!(0, runner.run_frame());
+
+    runner.get_clock_mut().advance(Duration::from_millis(250));
+    assert_eq!(2, runner.run_frame());
+    assert_eq!(2, get_steps(&runner));
+    assert_eq!(0.5, runner.get_alpha());
+    assert_eq!(0.5, runner.get_scene().get_resource::<RenderedAlpha>().unwrap().0);
+
+    runner.get_clock_mut().advance(Duration::from_millis(60));
+    assert_eq!(1, runner.run_frame());
+    assert_eq!(3, get_steps(&runner));
+    assert!((runner.get_alpha() - 0.1).abs() < 1e-4);
+}
+
+#[test]
+fn catch_up_is_capped() {
+    let mut runner = create_runner();
+    runner.set_max_catch_up_steps(3);
+    runner.run_frame();
+
+    runner.get_clock_mut().advance(Duration::from_millis(1050));
+    assert_eq!(3, runner.run_frame());
+    assert_eq!(0.5, runner.get_alpha());
+    assert_eq!(0, runner.run_frame());
+    assert_eq!(3, get_steps(&runner));
+}
+
+#[test]
+fn run_until_stops_after_the_condition_holds() {
+    let mut runner = create_runner();
+    let mut frames = 0;
+    runner.run_until(|scene| {
+        frames += 1;
+        scene.get_resource::<Time>().unwrap().get_frame_count() == 4
+    });
+
+    assert_eq!(4, frames);
+    assert_eq!(0, get_steps(&runner));
+    assert_eq!(4, runner.into_scene().get_resource::<Time>().unwrap().get_frame_count());
+}
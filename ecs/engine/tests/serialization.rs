@@ -0,0 +1,187 @@
+use engine::count_component::CountComponent;
+use engine::entity::Component;
+use engine::scene::Scene;
+use engine::serialization::SceneError;
+use engine::serialization::SkippedComponent;
+use serde::Deserialize;
+use serde::Serialize;
+use std::path::PathBuf;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Health {
+    current: u32,
+    max: u32,
+}
+
+impl Component for Health {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+struct MeshHandle;
+
+impl Component for MeshHandle {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+fn get_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("engine-serialization-{}-{}.json", name, std::process::id()))
+}
+
+fn create_registered_scene() -> Scene {
+    let mut scene = Scene::new();
+    scene.get_component_registry_mut().register::<CountComponent>("CountComponent");
+    scene.get_component_registry_mut().register::<Health>("Health");
+    scene
+}
+
+#[test]
+fn round_trip() {
+    let path = get_path("round_trip");
+    let mut scene = create_registered_scene();
+    let dog = scene.create_entity_with_name(String::from("dog"));
+    let removed = scene.create_entity();
+    let cat = scene.create_entity_with_name(String::from("cat"));
+    scene.destroy_entity(removed);
+    scene.add_component(cat, CountComponent::new());
+    scene.get_component_mut::<CountComponent>(cat).unwrap().override_count(7);
+    scene.add_component(cat, Health { current: 3, max: 9 });
+    scene.set_parent(cat, dog);
+    assert!(scene.save(&path).unwrap().is_complete());
+
+    let mut loaded = create_registered_scene();
+    loaded.create_entity();
+    assert!(loaded.load(&path).unwrap().is_complete());
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(2, loaded.get_entity_count());
+    assert_eq!("dog", loaded.get_entity_by_id(dog).unwrap().get_name());
+    assert_eq!("cat", loaded.get_entity_by_id(cat).unwrap().get_name());
+    assert_eq!(7, loaded.get_component::<CountComponent>(cat).unwrap().get_count());
+    assert_eq!(Some(&Health { current: 3, max: 9 }), loaded.get_component::<Health>(cat));
+    assert!(!loaded.has_component::<CountComponent>(dog));
+    assert_eq!(Some(dog), loaded.get_parent(cat));
+    assert!(!loaded.contains_entity(removed));
+
+    // The freed index is reused by new entities
+    let created = loaded.create_entity();
+    assert_eq!(removed.get_index(), created.get_index());
+}
+
+#[test]
+fn file_is_human_editable() {
+    let path = get_path("editable");
+    let mut scene = create_registered_scene();
+    let cat = scene.create_entity_with_name(String::from("cat"));
+    scene.add_component(cat, CountComponent::new());
+    scene.save(&path).unwrap();
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(text.contains("\"name\": \"cat\""));
+    std::fs::write(&path, text.replace("\"count\": 0", "\"count\": 42")).unwrap();
+
+    let mut loaded = create_registered_scene();
+    loaded.load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(42, loaded.get_component::<CountComponent>(cat).unwrap().get_count());
+}
+
+#[test]
+fn unregistered_components_are_reported() {
+    let path = get_path("unregistered");
+    let mut scene = create_registered_scene();
+    let rock = scene.create_entity();
+    scene.add_component(rock, MeshHandle);
+    scene.add_component(rock, Health { current: 1, max: 1 });
+
+    let report = scene.save(&path).unwrap();
+    let expected = SkippedComponent {
+        entity: rock,
+        type_name: String::from("serialization::MeshHandle"),
+    };
+    assert_eq!(&[expected], report.get_skipped());
+
+    let mut loaded = Scene::new();
+    let report = loaded.load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let expected = SkippedComponent { entity: rock, type_name: String::from("Health") };
+    assert_eq!(&[expected], report.get_skipped());
+    assert!(loaded.contains_entity(rock));
+}
+
+#[test]
+fn duplicate_entities_are_rejected() {
+    let path = get_path("duplicate");
+    let entity = r#"{ "id": { "index": 0, "generation": 0 }, "name": "a", "components": {} }"#;
+    std::fs::write(&path, format!("{{ \"entities\": [{}, {}] }}", entity, entity)).unwrap();
+
+    let mut scene = Scene::new();
+    let kept = scene.create_entity();
+    let result = scene.load(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    match result {
+        Err(SceneError::DuplicateEntity(id)) => assert_eq!(kept, id),
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(scene.contains_entity(kept));
+}
+
+fn load_text(scene: &mut Scene, name: &str, text: &str) -> Result<(), SceneError> {
+    let path = get_path(name);
+    std::fs::write(&path, text).unwrap();
+    let result = scene.load(&path);
+    std::fs::remove_file(&path).unwrap();
+    result.map(|_| ())
+}
+
+#[test]
+fn invalid_files_leave_the_scene_unchanged() {
+    let mut scene = create_registered_scene();
+    scene.get_history_mut().set_recording(true);
+    let kept = scene.create_entity_with_name(String::from("keep"));
+    scene.add_component(kept, Health { current: 1, max: 2 });
+
+    let invalid_value = r#"{ "entities": [
+        { "id": { "index": 0, "generation": 0 }, "name": "a", "components": {} },
+        { "id": { "index": 1, "generation": 0 }, "name": "b", "components": { "CountComponent": { "count": "oops" } } }
+    ] }"#;
+    match load_text(&mut scene, "invalid_value", invalid_value) {
+        Err(SceneError::Format(_)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    let same_index = r#"{ "entities": [
+        { "id": { "index": 3, "generation": 0 }, "name": "a", "components": {} },
+        { "id": { "index": 3, "generation": 1 }, "name": "b", "components": {} }
+    ] }"#;
+    match load_text(&mut scene, "same_index", same_index) {
+        Err(SceneError::DuplicateEntity(id)) => assert_eq!(1, id.get_generation()),
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    let cycle = r#"{ "entities": [
+        { "id": { "index": 0, "generation": 0 }, "name": "a", "parent": { "index": 1, "generation": 0 }, "components": {} },
+        { "id": { "index": 1, "generation": 0 }, "name": "b", "parent": { "index": 0, "generation": 0 }, "components": {} }
+    ] }"#;
+    match load_text(&mut scene, "cycle", cycle) {
+        Err(SceneError::ParentCycle(_)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    let huge_index = r#"{ "slot_count": 2, "entities": [
+        { "id": { "index": 4000000000, "generation": 0 }, "name": "a", "components": {} }
+    ] }"#;
+    match load_text(&mut scene, "huge_index", huge_index) {
+        Err(SceneError::InvalidIndex(id)) => assert_eq!(4_000_000_000, id.get_index()),
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    assert_eq!(1, scene.get_entity_count());
+    assert_eq!("keep", scene.get_entity_by_id(kept).unwrap().get_name());
+    assert_eq!(Some(&Health { current: 1, max: 2 }), scene.get_component::<Health>(kept));
+    assert!(scene.get_history().can_undo());
+}
@@ -0,0 +1,170 @@
+use engine::count_component::CountComponent;
+use engine::entity::Component;
+use engine::entity::EntityId;
+use engine::entity::UpdateContext;
+use engine::scene::Scene;
+use engine::serialization::SkippedComponent;
+use serde::Deserialize;
+use serde::Serialize;
+
+// Spawns a counted child every other update
+#[derive(Clone)]
+struct Spawner {
+    updates: u32,
+}
+
+impl Component for Spawner {
+    fn update(&mut self, context: &mut UpdateContext) {
+        self.updates += 1;
+        if self.updates % 2 == 1 {
+            let parent = context.get_entity();
+            let name = format!("spawned at {}", self.updates);
+            context
+                .get_commands()
+                .spawn_with_name(name)
+                .add_component(CountComponent::new())
+                .set_parent(parent);
+        }
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Health {
+    current: u32,
+}
+
+impl Component for Health {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Velocity {
+    speed: f32,
+}
+
+impl Component for Velocity {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+struct MeshHandle;
+
+impl Component for MeshHandle {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+fn create_registered_scene() -> Scene {
+    let mut scene = Scene::new();
+    scene.get_component_registry_mut().register_clone::<CountComponent>();
+    scene.get_component_registry_mut().register_clone::<Spawner>();
+    scene.get_component_registry_mut().register::<Health>("Health");
+    scene.get_component_registry_mut().register::<Velocity>("Velocity");
+    scene
+}
+
+fn get_state(scene: &mut Scene) -> Vec<(EntityId, String, Option<EntityId>, Option<u64>)> {
+    let ids: Vec<EntityId> = scene.query::<EntityId>().collect();
+    ids.into_iter()
+        .map(|id| {
+            let name = scene.get_entity_by_id(id).unwrap().get_name();
+            let count = scene.get_component::<CountComponent>(id).map(|x| x.get_count());
+            (id, name, scene.get_parent(id), count)
+        })
+        .collect()
+}
+
+#[test]
+fn resimulation_matches_after_restore() {
+    let mut scene = create_registered_scene();
+    for _ in 0..2 {
+        let spawner = scene.create_entity();
+        scene.add_component(spawner, Spawner { updates: 0 });
+    }
+    for _ in 0..3 {
+        scene.update();
+    }
+
+    let snapshot = scene.snapshot();
+    assert!(snapshot.is_complete());
+    assert_eq!(6, snapshot.get_entity_count());
+    let before = get_state(&mut scene);
+    for _ in 0..5 {
+        scene.update();
+    }
+    let simulated = get_state(&mut scene);
+
+    scene.restore(&snapshot);
+    assert_eq!(before, get_state(&mut scene));
+    for _ in 0..5 {
+        scene.update();
+    }
+    assert_eq!(simulated, get_state(&mut scene));
+}
+
+#[test]
+fn restored_ids_stay_valid() {
+    let mut scene = create_registered_scene();
+    let kept = scene.create_entity_with_name(String::from("kept"));
+    let destroyed = scene.create_entity_with_name(String::from("destroyed"));
+    scene.add_component(destroyed, Health { current: 3 });
+    scene.set_parent(destroyed, kept);
+    let snapshot = scene.snapshot();
+
+    scene.destroy_entity(kept);
+    let created = scene.create_entity();
+    assert_eq!(kept.get_index(), created.get_index());
+
+    scene.restore(&snapshot);
+    assert!(!scene.contains_entity(created));
+    assert_eq!(Some(&Health { current: 3 }), scene.get_component::<Health>(destroyed));
+    assert_eq!(&[destroyed], scene.get_children(kept));
+    assert_eq!(Some(kept), scene.get_entity_by_name("kept"));
+    assert_eq!(0, scene.get_removed_components::<Health>().count());
+    let next = scene.create_entity();
+    scene.restore(&snapshot);
+    assert_eq!(next, scene.create_entity());
+}
+
+#[test]
+fn unregistered_components_are_skipped() {
+    let mut scene = create_registered_scene();
+    let rock = scene.create_entity();
+    scene.add_component(rock, MeshHandle);
+    scene.add_component(rock, Health { current: 1 });
+
+    let snapshot = scene.snapshot();
+    let expected = SkippedComponent {
+        entity: rock,
+        type_name: String::from("snapshot::MeshHandle"),
+    };
+    assert_eq!(&[expected], snapshot.get_skipped());
+
+    scene.restore(&snapshot);
+    assert!(!scene.has_component::<MeshHandle>(rock));
+    assert!(scene.has_component::<Health>(rock));
+}
+
+#[test]
+fn components_that_do_not_survive_saving_are_skipped() {
+    let mut scene = create_registered_scene();
+    let comet = scene.create_entity();
+    scene.add_component(comet, Velocity { speed: f32::NAN });
+    scene.add_component(comet, Health { current: 2 });
+
+    let snapshot = scene.snapshot();
+    let expected = SkippedComponent { entity: comet, type_name: String::from("snapshot::Velocity") };
+    assert_eq!(&[expected], snapshot.get_skipped());
+
+    scene.restore(&snapshot);
+    assert!(!scene.has_component::<Velocity>(comet));
+    assert_eq!(Some(&Health { current: 2 }), scene.get_component::<Health>(comet));
+}
@@ -0,0 +1,157 @@
+use engine::entity::Component;
+use engine::entity::UpdateContext;
+use engine::scene::Scene;
+use engine::schedule::Stage;
+use engine::state::NextState;
+use engine::state::OnEnter;
+use engine::state::OnExit;
+use engine::state::State;
+use engine::system::FunctionSystem;
+use engine::system::SystemContext;
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+enum GameState {
+    Loading,
+    Menu,
+    Playing,
+    Paused,
+}
+
+#[derive(Default)]
+struct Log {
+    entries: Vec<&'static str>,
+}
+
+struct Ticker {
+    updates: u32,
+}
+
+impl Component for Ticker {
+    fn update(&mut self, _context: &mut UpdateContext) {
+        self.updates += 1;
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+fn logger(entry: &'static str) -> FunctionSystem<impl FnMut(&mut SystemContext) + Send + Sync> {
+    FunctionSystem::new(entry, move |context: &mut SystemContext| {
+        context.get_resource_mut::<Log>().unwrap().entries.push(entry);
+    })
+    .writes_resource::<Log>()
+}
+
+fn take_log(scene: &mut Scene) -> Vec<&'static str> {
+    std::mem::take(&mut scene.get_resource_mut::<Log>().unwrap().entries)
+}
+
+fn request(scene: &mut Scene, state: GameState) {
+    scene.get_resource_mut::<NextState<GameState>>().unwrap().set(state);
+}
+
+fn get_state(scene: &Scene) -> GameState {
+    scene.get_resource::<State<GameState>>().unwrap().get().clone()
+}
+
+fn create_scene() -> Scene {
+    let mut scene = Scene::new();
+    scene.get_schedule_mut().set_parallel(false);
+    scene.insert_resource(Log::default());
+    scene.init_state(GameState::Loading);
+    // Loading finishes within its first frame
+    let load = FunctionSystem::new("load", |context: &mut SystemContext| {
+        context.get_resource_mut::<Log>().unwrap().entries.push("load");
+        context.get_resource_mut::<NextState<GameState>>().unwrap().set(GameState::Menu);
+    })
+    .writes_resource::<Log>()
+    .writes_resource::<NextState<GameState>>();
+    scene.add_system(Stage::Update, load).run_in_state(GameState::Loading);
+    scene.add_system(Stage::Update, logger("menu")).run_in_state(GameState::Menu);
+    scene.add_system(Stage::Update, logger("gameplay")).run_in_state(GameState::Playing);
+    scene.add_system(Stage::Update, logger("always"));
+    scene.add_transition_system(OnEnter(GameState::Loading), logger("enter loading"));
+    scene.add_transition_system(OnExit(GameState::Loading), logger("exit loading"));
+    scene.add_transition_system(OnEnter(GameState::Menu), logger("enter menu"));
+    scene.add_transition_system(OnExit(GameState::Menu), logger("exit menu"));
+    scene.add_transition_system(OnEnter(GameState::Playing), logger("enter playing"));
+    scene.add_transition_system(OnExit(GameState::Playing), logger("exit playing"));
+    scene.add_transition_system(OnEnter(GameState::Paused), logger("enter paused"));
+    scene.add_transition_system(OnExit(GameState::Paused), logger("exit paused"));
+    scene
+}
+
+#[test]
+fn transitions_run_their_schedules_once() {
+    let mut scene = create_scene();
+
+    scene.update();
+    assert_eq!(vec!["enter loading", "load", "always"], take_log(&mut scene));
+    assert_eq!(GameState::Loading, get_state(&scene));
+
+    scene.update();
+    assert_eq!(vec!["exit loading", "enter menu", "menu", "always"], take_log(&mut scene));
+    scene.update();
+    assert_eq!(vec!["menu", "always"], take_log(&mut scene));
+
+    request(&mut scene, GameState::Playing);
+    scene.update();
+    assert_eq!(vec!["exit menu", "enter playing", "gameplay", "always"], take_log(&mut scene));
+
+    request(&mut scene, GameState::Paused);
+    scene.update();
+    assert_eq!(vec!["exit playing", "enter paused", "always"], take_log(&mut scene));
+    scene.update();
+    assert_eq!(vec!["always"], take_log(&mut scene));
+
+    request(&mut scene, GameState::Playing);
+    scene.update();
+    assert_eq!(vec!["exit paused", "enter playing", "gameplay", "always"], take_log(&mut scene));
+    assert_eq!(GameState::Playing, get_state(&scene));
+}
+
+#[test]
+fn only_the_last_request_of_a_frame_is_applied() {
+    let mut scene = create_scene();
+    scene.update();
+    scene.update();
+    take_log(&mut scene);
+
+    request(&mut scene, GameState::Playing);
+    request(&mut scene, GameState::Paused);
+    scene.update();
+    assert_eq!(vec!["exit menu", "enter paused", "always"], take_log(&mut scene));
+
+    // Requesting the current state is not a transition
+    request(&mut scene, GameState::Paused);
+    scene.update();
+    assert_eq!(vec!["always"], take_log(&mut scene));
+    assert!(scene.get_resource::<NextState<GameState>>().unwrap().get().is_none());
+}
+
+#[test]
+fn component_updates_pause_outside_of_their_state() {
+    let mut scene = create_scene();
+    scene.run_component_updates_in_state(GameState::Playing);
+    let ticker = scene.create_entity();
+    scene.add_component(ticker, Ticker { updates: 0 });
+
+    scene.update();
+    scene.update();
+    request(&mut scene, GameState::Playing);
+    scene.update();
+    scene.update();
+    request(&mut scene, GameState::Paused);
+    scene.update();
+    scene.update();
+    assert_eq!(2, scene.get_component::<Ticker>(ticker).unwrap().updates);
+}
+
+#[test]
+#[should_panic(expected = "is not initialized")]
+fn transition_systems_need_an_initialized_state() {
+    let mut scene = Scene::new();
+    scene.insert_resource(Log::default());
+    scene.add_transition_system(OnEnter(GameState::Menu), logger("enter menu"));
+}
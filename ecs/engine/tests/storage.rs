@@ -0,0 +1,203 @@
+use engine::entity::Component;
+use engine::entity::EntityId;
+use engine::query::Access;
+use engine::query::Added;
+use engine::query::Changed;
+use engine::query::Fetch;
+use engine::query::QueryFilter;
+use engine::query::With;
+use engine::query::Without;
+use engine::scene::Scene;
+use engine::schedule::Stage;
+use engine::storage::StorageType;
+use engine::system::FunctionSystem;
+use engine::system::SystemContext;
+use std::any::TypeId;
+
+struct Position {
+    x: f32,
+}
+
+impl Component for Position {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+// Added and removed all the time, so kept out of the archetype tables
+struct Burning {
+    damage: u32,
+}
+
+impl Component for Burning {
+    const STORAGE: StorageType = StorageType::SparseSet;
+
+    fn on_remove(&mut self, _entity: EntityId) {
+        self.damage = 0;
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+fn create_scene() -> (Scene, Vec<EntityId>) {
+    let mut scene = Scene::new();
+    let ids: Vec<EntityId> = (0..4)
+        .map(|x| {
+            let id = scene.create_entity();
+            scene.add_component(id, Position { x: x as f32 });
+            id
+        })
+        .collect();
+    (scene, ids)
+}
+
+#[test]
+fn sparse_components_do_not_move_entities_between_archetypes() {
+    let (mut scene, ids) = create_scene();
+    let archetype_count = scene.get_archetypes().len();
+
+    scene.add_component(ids[1], Burning { damage: 3 });
+    assert!(scene.has_component::<Burning>(ids[1]));
+    assert!(!scene.has_component::<Burning>(ids[0]));
+    assert_eq!(archetype_count, scene.get_archetypes().len());
+    assert_eq!(ids, scene.get_archetypes().last().unwrap().get_entities());
+
+    scene.get_component_mut::<Burning>(ids[1]).unwrap().damage += 1;
+    assert_eq!(4, scene.get_component::<Burning>(ids[1]).unwrap().damage);
+    let types: Vec<&str> = scene.get_component_types(ids[1]).into_iter().map(|(_, x)| x).collect();
+    assert_eq!(vec!["storage::Position", "storage::Burning"], types);
+
+    // The remove hook runs on the taken component
+    assert_eq!(0, scene.take_component::<Burning>(ids[1]).unwrap().damage);
+    assert!(!scene.has_component::<Burning>(ids[1]));
+    assert_eq!(vec![ids[1]], scene.get_removed_components::<Burning>().collect::<Vec<_>>());
+}
+
+#[test]
+fn queries_mix_table_and_sparse_components() {
+    let (mut scene, ids) = create_scene();
+    scene.add_component(ids[1], Burning { damage: 1 });
+    scene.add_component(ids[3], Burning { damage: 2 });
+    let burning_without_position = scene.create_entity();
+    scene.add_component(burning_without_position, Burning { damage: 5 });
+
+    let burning: Vec<(EntityId, u32)> = scene
+        .query::<(EntityId, &Position, &Burning)>()
+        .map(|(id, _, burning)| (id, burning.damage))
+        .collect();
+    assert_eq!(vec![(ids[1], 1), (ids[3], 2)], burning);
+
+    let damage: Vec<Option<u32>> = scene
+        .query::<(&Position, Option<&Burning>)>()
+        .map(|(_, x)| x.map(|x| x.damage))
+        .collect();
+    assert_eq!(vec![None, Some(1), None, Some(2)], damage);
+
+    let with: Vec<EntityId> = scene.query_filtered::<EntityId, With<Burning>>().collect();
+    assert_eq!(vec![burning_without_position, ids[1], ids[3]], with);
+    let without: Vec<EntityId> = scene.query_filtered::<EntityId, (With<Position>, Without<Burning>)>().collect();
+    assert_eq!(vec![ids[0], ids[2]], without);
+
+    for (position, burning) in scene.query::<(&mut Position, &mut Burning)>() {
+        position.x -= burning.damage as f32;
+        burning.damage += 1;
+    }
+    assert_eq!(1.0, scene.get_component::<Position>(ids[3]).unwrap().x);
+    assert_eq!(3, scene.get_component::<Burning>(ids[3]).unwrap().damage);
+}
+
+#[test]
+fn change_detection_covers_sparse_components() {
+    let (mut scene, ids) = create_scene();
+    scene.add_component(ids[0], Burning { damage: 1 });
+    scene.update();
+    scene.add_component(ids[2], Burning { damage: 1 });
+
+    let added: Vec<EntityId> = scene.query_filtered::<EntityId, Added<Burning>>().collect();
+    assert_eq!(vec![ids[2]], added);
+    scene.update();
+    scene.get_component_mut::<Burning>(ids[0]).unwrap();
+    let changed: Vec<EntityId> = scene.query_filtered::<EntityId, Changed<Burning>>().collect();
+    assert_eq!(vec![ids[0]], changed);
+}
+
+#[test]
+fn sparse_filters_read_their_set() {
+    let mut access = Access::new();
+    <(With<Burning>, Without<Position>) as QueryFilter>::add_access(&mut access);
+    assert!(access.reads(TypeId::of::<Burning>()));
+    assert!(!access.reads(TypeId::of::<Position>()));
+
+    let mut access = Access::new();
+    <&mut Burning as Fetch>::add_access(&mut access);
+    <With<Burning> as QueryFilter>::add_access(&mut access);
+    assert!(access.writes(TypeId::of::<Burning>()));
+    assert!(!access.reads(TypeId::of::<Burning>()));
+}
+
+#[test]
+fn systems_query_sparse_components_in_parallel() {
+    let (mut scene, ids) = create_scene();
+    for id in ids.iter() {
+        scene.add_component(*id, Burning { damage: 2 });
+    }
+    scene.get_schedule_mut().set_parallel(true);
+    scene.get_schedule_mut().set_debug(true);
+    let burn = FunctionSystem::new("burn", |context: &mut SystemContext| {
+        let mut extinguished = vec![];
+        for (id, burning) in context.query::<(EntityId, &mut Burning)>() {
+            burning.damage -= 1;
+            if burning.damage == 0 {
+                extinguished.push(id);
+            }
+        }
+        for id in extinguished {
+            context.get_commands().entity(id).remove_component::<Burning>();
+        }
+    })
+    .writes::<Burning>();
+    let sink = FunctionSystem::new("sink", |context: &mut SystemContext| {
+        for position in context.query::<&mut Position>() {
+            position.x -= 1.0;
+        }
+    })
+    .writes::<Position>();
+    scene.add_system(Stage::Update, burn);
+    scene.add_system(Stage::Update, sink);
+
+    scene.update();
+    assert_eq!(4, scene.query::<&Burning>().count());
+    scene.update();
+    assert_eq!(0, scene.query::<&Burning>().count());
+    assert_eq!(1.0, scene.get_component::<Position>(ids[3]).unwrap().x);
+    let batches = scene.get_schedule().get_execution_graph().unwrap().get_batches(Stage::Update);
+    assert_eq!(vec![vec!["burn", "sink"]], batches);
+}
+
+#[test]
+fn destroying_and_moving_entities_takes_sparse_components() {
+    let (mut scene, ids) = create_scene();
+    scene.add_component(ids[0], Burning { damage: 1 });
+    scene.add_component(ids[1], Burning { damage: 2 });
+
+    scene.destroy_entity(ids[0]);
+    assert_eq!(vec![ids[0]], scene.get_removed_components::<Burning>().collect::<Vec<_>>());
+    let reused = scene.create_entity();
+    assert_eq!(ids[0].get_index(), reused.get_index());
+    assert!(!scene.has_component::<Burning>(reused));
+
+    let mut other = Scene::new();
+    let map = scene.move_entity_to(&mut other, ids[1]);
+    let moved = map.get(ids[1]).unwrap();
+    assert_eq!(0, scene.query::<&Burning>().count());
+    assert_eq!(2, other.get_component::<Burning>(moved).unwrap().damage);
+    assert_eq!(
+        vec![moved],
+        other
+            .query::<(EntityId, &Position, &Burning)>()
+            .map(|(x, _, _)| x)
+            .collect::<Vec<_>>()
+    );
+}
@@ -0,0 +1,124 @@
+use engine::count_component::CountComponent;
+use engine::entity::Component;
+use engine::scene::Scene;
+use engine::schedule::Stage;
+use engine::system::FunctionSystem;
+use engine::system::SystemContext;
+
+struct Log {
+    entries: Vec<String>,
+}
+
+impl Component for Log {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+fn log_system(name: &'static str) -> FunctionSystem<impl FnMut(&mut SystemContext)> {
+    FunctionSystem::new(name, move |context: &mut SystemContext| {
+        for log in context.query::<&mut Log>() {
+            log.entries.push(String::from(name));
+        }
+    })
+    .writes::<Log>()
+}
+
+fn create_log_scene() -> Scene {
+    let mut scene = Scene::new();
+    let entity = scene.create_entity_with_name(String::from("log"));
+    scene.add_component(entity, Log { entries: vec![] });
+    scene
+}
+
+fn get_log(scene: &mut Scene) -> Vec<String> {
+    scene.query::<&Log>().next().unwrap().entries.clone()
+}
+
+#[test]
+fn stages_run_in_order() {
+    let mut scene = create_log_scene();
+    scene.add_system(Stage::Render, log_system("render"));
+    scene.add_system(Stage::PostUpdate, log_system("post_update"));
+    scene.add_system(Stage::Update, log_system("update"));
+    scene.add_system(Stage::PreUpdate, log_system("pre_update"));
+
+    scene.update();
+    scene.update();
+
+    let expected = ["pre_update", "update", "post_update", "render"];
+    assert_eq!(expected.repeat(2), get_log(&mut scene));
+}
+
+#[test]
+fn before_and_after_constraints() {
+    let mut scene = create_log_scene();
+    scene.add_system(Stage::Update, log_system("physics")).after("input");
+    scene.add_system(Stage::Update, log_system("input"));
+    scene
+        .add_system(Stage::Update, log_system("animation"))
+        .before("physics")
+        .after("input");
+    scene.add_system(Stage::Update, log_system("audio"));
+
+    scene.update();
+
+    let expected = vec!["input", "animation", "physics", "audio"];
+    assert_eq!(expected, get_log(&mut scene));
+    assert_eq!(expected, scene.get_schedule_mut().get_system_names(Stage::Update));
+}
+
+#[test]
+fn component_updates_run_before_update_stage() {
+    let mut scene = create_log_scene();
+    let entity = scene.create_entity();
+    scene.add_component(entity, CountComponent::new());
+    let record = |name: &'static str| {
+        FunctionSystem::new(name, move |context: &mut SystemContext| {
+            let count = context.query::<&CountComponent>().next().unwrap().get_count();
+            for log in context.query::<&mut Log>() {
+                log.entries.push(format!("{} {}", name, count));
+            }
+        })
+        .reads::<CountComponent>()
+        .writes::<Log>()
+    };
+    scene.add_system(Stage::PreUpdate, record("pre_update"));
+    scene.add_system(Stage::Update, record("update"));
+
+    scene.update();
+
+    assert_eq!(vec!["pre_update 0", "update 1"], get_log(&mut scene));
+}
+
+#[test]
+#[should_panic(expected = "cyclic ordering constraints")]
+fn cyclic_constraints() {
+    let mut scene = create_log_scene();
+    scene.add_system(Stage::Update, log_system("a")).before("b");
+    scene.add_system(Stage::Update, log_system("b")).before("a");
+    scene.update();
+}
+
+#[test]
+#[should_panic(expected = "not in stage Update")]
+fn constraint_on_other_stage() {
+    let mut scene = create_log_scene();
+    scene.add_system(Stage::PreUpdate, log_system("input"));
+    scene.add_system(Stage::Update, log_system("physics")).after("input");
+    scene.update();
+}
+
+#[test]
+#[should_panic(expected = "system sneaky accesses engine::count_component::CountComponent without declaring it")]
+fn undeclared_access() {
+    let mut scene = Scene::new();
+    let entity = scene.create_entity();
+    scene.add_component(entity, CountComponent::new());
+    let sneaky = FunctionSystem::new("sneaky", |context: &mut SystemContext| {
+        context.query::<&mut CountComponent>().count();
+    })
+    .reads::<CountComponent>();
+    scene.add_system(Stage::Update, sneaky);
+    scene.update();
+}